@@ -0,0 +1,3 @@
+ALTER TABLE template DROP COLUMN pinned;
+ALTER TABLE template DROP COLUMN commit_sha;
+ALTER TABLE template DROP COLUMN git_ref;
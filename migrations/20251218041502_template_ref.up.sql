@@ -0,0 +1,5 @@
+-- ------------------------------------------------ template: resolved git ref
+
+ALTER TABLE template ADD COLUMN git_ref TEXT;
+ALTER TABLE template ADD COLUMN commit_sha TEXT;
+ALTER TABLE template ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;
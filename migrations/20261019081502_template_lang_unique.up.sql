@@ -0,0 +1,72 @@
+-- ------------------------------------------------ template: one row per lang
+
+-- Every lang of a template gets its own row (sharing the installed dir), so `lang` has to be part
+-- of the unique key. SQLite can't change a table's constraints, so the table is rebuilt.
+
+-- template_content references the old table, so its rows are set aside while the table is replaced.
+CREATE TABLE template_content_backup AS SELECT * FROM template_content;
+
+CREATE TABLE template_new (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  name TEXT NOT NULL,
+  lang TEXT,
+  template_dir TEXT,
+  created_at TIMESTAMP NOT NULL,
+  updated_at TIMESTAMP,
+  repo TEXT,
+  branch TEXT,
+  subdir TEXT,
+  sha256_hash TEXT NOT NULL UNIQUE,
+  git_ref TEXT,
+  commit_sha TEXT,
+  pinned INTEGER NOT NULL DEFAULT 0,
+  UNIQUE (name, lang, repo, branch, subdir)
+);
+
+INSERT INTO template_new
+  (id, name, lang, template_dir, created_at, updated_at, repo, branch, subdir, sha256_hash,
+   git_ref, commit_sha, pinned)
+SELECT id, name, lang, template_dir, created_at, updated_at, repo, branch, subdir, sha256_hash,
+       git_ref, commit_sha, pinned
+FROM template;
+
+DELETE FROM template_content;
+DROP TABLE template;
+ALTER TABLE template_new RENAME TO template;
+
+-- .................. template after insert
+CREATE TRIGGER IF NOT EXISTS template_after_insert AFTER INSERT ON template BEGIN
+    INSERT INTO template_fts
+      (rowid, name, lang, template_dir, repo, branch, subdir, sha256_hash)
+    VALUES
+      (new.id, new.name, new.lang, new.template_dir, new.repo, new.branch, new.subdir, new.sha256_hash);
+END;
+
+-- .................. template after update
+CREATE TRIGGER IF NOT EXISTS template_after_update AFTER UPDATE ON template BEGIN
+    INSERT INTO template_fts
+      (template_fts, rowid, name, lang, template_dir, repo, branch, subdir, sha256_hash)
+    VALUES
+      ('delete', old.rowid, old.name, old.lang, old.template_dir, old.repo, old.branch, old.subdir, old.sha256_hash);
+
+    INSERT INTO template_fts
+      (rowid, name, lang, template_dir, repo, branch, subdir, sha256_hash)
+    VALUES
+      (new.id, new.name, new.lang, new.template_dir, new.repo, new.branch, new.subdir, new.sha256_hash);
+END;
+
+-- .................. template after delete
+CREATE TRIGGER IF NOT EXISTS template_after_delete AFTER DELETE ON template BEGIN
+    INSERT INTO template_fts
+      (template_fts, rowid, name, lang, template_dir, repo, branch, subdir, sha256_hash)
+    VALUES
+      ('delete', old.rowid, old.name, old.lang, old.template_dir, old.repo, old.branch, old.subdir, old.sha256_hash);
+END;
+
+INSERT INTO template_content
+  (id, template_id, file_path, content, created_at, updated_at)
+SELECT id, template_id, file_path, content, created_at, updated_at
+FROM template_content_backup
+WHERE template_id IN (SELECT id FROM template);
+
+DROP TABLE template_content_backup;
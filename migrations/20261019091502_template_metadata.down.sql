@@ -0,0 +1,108 @@
+-- The FTS tables go back to their old columns before the metadata columns are dropped.
+
+DROP TRIGGER IF EXISTS template_after_insert;
+DROP TRIGGER IF EXISTS template_after_update;
+DROP TRIGGER IF EXISTS template_after_delete;
+DROP TABLE IF EXISTS template_fts;
+
+DROP TRIGGER IF EXISTS source_template_after_insert;
+DROP TRIGGER IF EXISTS source_template_after_update;
+DROP TRIGGER IF EXISTS source_template_after_delete;
+DROP TABLE IF EXISTS source_template_fts;
+
+ALTER TABLE template DROP COLUMN repository;
+ALTER TABLE template DROP COLUMN website;
+ALTER TABLE template DROP COLUMN keywords;
+ALTER TABLE template DROP COLUMN license;
+ALTER TABLE template DROP COLUMN authors;
+ALTER TABLE template DROP COLUMN version;
+ALTER TABLE template DROP COLUMN description;
+
+ALTER TABLE source_template DROP COLUMN repository;
+ALTER TABLE source_template DROP COLUMN website;
+ALTER TABLE source_template DROP COLUMN keywords;
+ALTER TABLE source_template DROP COLUMN license;
+ALTER TABLE source_template DROP COLUMN authors;
+ALTER TABLE source_template DROP COLUMN version;
+ALTER TABLE source_template DROP COLUMN description;
+
+CREATE VIRTUAL TABLE template_fts USING fts5(
+  name,
+  lang,
+  template_dir,
+  repo,
+  branch,
+  subdir,
+  sha256_hash,
+  content='template',
+  content_rowid='id'
+);
+
+-- .................. template after insert
+CREATE TRIGGER IF NOT EXISTS template_after_insert AFTER INSERT ON template BEGIN
+    INSERT INTO template_fts
+      (rowid, name, lang, template_dir, repo, branch, subdir, sha256_hash)
+    VALUES
+      (new.id, new.name, new.lang, new.template_dir, new.repo, new.branch, new.subdir, new.sha256_hash);
+END;
+
+-- .................. template after update
+CREATE TRIGGER IF NOT EXISTS template_after_update AFTER UPDATE ON template BEGIN
+    INSERT INTO template_fts
+      (template_fts, rowid, name, lang, template_dir, repo, branch, subdir, sha256_hash)
+    VALUES
+      ('delete', old.rowid, old.name, old.lang, old.template_dir, old.repo, old.branch, old.subdir, old.sha256_hash);
+
+    INSERT INTO template_fts
+      (rowid, name, lang, template_dir, repo, branch, subdir, sha256_hash)
+    VALUES
+      (new.id, new.name, new.lang, new.template_dir, new.repo, new.branch, new.subdir, new.sha256_hash);
+END;
+
+-- .................. template after delete
+CREATE TRIGGER IF NOT EXISTS template_after_delete AFTER DELETE ON template BEGIN
+    INSERT INTO template_fts
+      (template_fts, rowid, name, lang, template_dir, repo, branch, subdir, sha256_hash)
+    VALUES
+      ('delete', old.rowid, old.name, old.lang, old.template_dir, old.repo, old.branch, old.subdir, old.sha256_hash);
+END;
+
+INSERT INTO template_fts(template_fts) VALUES('rebuild');
+
+CREATE VIRTUAL TABLE IF NOT EXISTS source_template_fts USING fts5(
+  repo,
+  lang,
+  name,
+  branch,
+  subdir,
+  sha256_hash,
+  content='source_template',
+  content_rowid='id'
+);
+
+-- after insert
+CREATE TRIGGER IF NOT EXISTS source_template_after_insert
+AFTER INSERT ON source_template
+BEGIN
+  INSERT INTO source_template_fts(rowid, repo, lang, name, branch, subdir, sha256_hash)
+  VALUES (new.id, new.repo, new.lang, new.name, new.branch, new.subdir, new.sha256_hash);
+END;
+
+-- after update
+CREATE TRIGGER IF NOT EXISTS source_template_after_update
+AFTER UPDATE ON source_template
+BEGIN
+  INSERT INTO source_template_fts(source_template_fts, rowid) VALUES('delete', old.id);
+
+  INSERT INTO source_template_fts(rowid, repo, lang, name, branch, subdir, sha256_hash)
+  VALUES (new.id, new.repo, new.lang, new.name, new.branch, new.subdir, new.sha256_hash);
+END;
+
+-- after delete
+CREATE TRIGGER IF NOT EXISTS source_template_after_delete
+AFTER DELETE ON source_template
+BEGIN
+  INSERT INTO source_template_fts(source_template_fts, rowid) VALUES('delete', old.id);
+END;
+
+INSERT INTO source_template_fts(source_template_fts) VALUES('rebuild');
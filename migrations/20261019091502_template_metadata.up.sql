@@ -0,0 +1,141 @@
+-- ------------------------------------------------ template/source_template: [project] metadata
+
+-- The `[project]` table of boilermaker.toml. `authors` and `keywords` are stored comma-separated.
+-- The FTS tables index the new columns too, so they're recreated and rebuilt from their tables.
+
+ALTER TABLE template ADD COLUMN description TEXT;
+ALTER TABLE template ADD COLUMN version TEXT;
+ALTER TABLE template ADD COLUMN authors TEXT;
+ALTER TABLE template ADD COLUMN license TEXT;
+ALTER TABLE template ADD COLUMN keywords TEXT;
+ALTER TABLE template ADD COLUMN website TEXT;
+ALTER TABLE template ADD COLUMN repository TEXT;
+
+ALTER TABLE source_template ADD COLUMN description TEXT;
+ALTER TABLE source_template ADD COLUMN version TEXT;
+ALTER TABLE source_template ADD COLUMN authors TEXT;
+ALTER TABLE source_template ADD COLUMN license TEXT;
+ALTER TABLE source_template ADD COLUMN keywords TEXT;
+ALTER TABLE source_template ADD COLUMN website TEXT;
+ALTER TABLE source_template ADD COLUMN repository TEXT;
+
+-- ------------------------------------------------ template_fts
+
+DROP TRIGGER IF EXISTS template_after_insert;
+DROP TRIGGER IF EXISTS template_after_update;
+DROP TRIGGER IF EXISTS template_after_delete;
+DROP TABLE IF EXISTS template_fts;
+
+CREATE VIRTUAL TABLE template_fts USING fts5(
+  name,
+  lang,
+  template_dir,
+  repo,
+  branch,
+  subdir,
+  sha256_hash,
+  description,
+  version,
+  authors,
+  license,
+  keywords,
+  website,
+  repository,
+  content='template',
+  content_rowid='id'
+);
+
+-- .................. template after insert
+CREATE TRIGGER template_after_insert AFTER INSERT ON template BEGIN
+    INSERT INTO template_fts
+      (rowid, name, lang, template_dir, repo, branch, subdir, sha256_hash,
+       description, version, authors, license, keywords, website, repository)
+    VALUES
+      (new.id, new.name, new.lang, new.template_dir, new.repo, new.branch, new.subdir, new.sha256_hash,
+       new.description, new.version, new.authors, new.license, new.keywords, new.website, new.repository);
+END;
+
+-- .................. template after update
+CREATE TRIGGER template_after_update AFTER UPDATE ON template BEGIN
+    INSERT INTO template_fts
+      (template_fts, rowid, name, lang, template_dir, repo, branch, subdir, sha256_hash,
+       description, version, authors, license, keywords, website, repository)
+    VALUES
+      ('delete', old.rowid, old.name, old.lang, old.template_dir, old.repo, old.branch, old.subdir, old.sha256_hash,
+       old.description, old.version, old.authors, old.license, old.keywords, old.website, old.repository);
+
+    INSERT INTO template_fts
+      (rowid, name, lang, template_dir, repo, branch, subdir, sha256_hash,
+       description, version, authors, license, keywords, website, repository)
+    VALUES
+      (new.id, new.name, new.lang, new.template_dir, new.repo, new.branch, new.subdir, new.sha256_hash,
+       new.description, new.version, new.authors, new.license, new.keywords, new.website, new.repository);
+END;
+
+-- .................. template after delete
+CREATE TRIGGER template_after_delete AFTER DELETE ON template BEGIN
+    INSERT INTO template_fts
+      (template_fts, rowid, name, lang, template_dir, repo, branch, subdir, sha256_hash,
+       description, version, authors, license, keywords, website, repository)
+    VALUES
+      ('delete', old.rowid, old.name, old.lang, old.template_dir, old.repo, old.branch, old.subdir, old.sha256_hash,
+       old.description, old.version, old.authors, old.license, old.keywords, old.website, old.repository);
+END;
+
+INSERT INTO template_fts(template_fts) VALUES('rebuild');
+
+-- ------------------------------------------------ source_template_fts
+
+DROP TRIGGER IF EXISTS source_template_after_insert;
+DROP TRIGGER IF EXISTS source_template_after_update;
+DROP TRIGGER IF EXISTS source_template_after_delete;
+DROP TABLE IF EXISTS source_template_fts;
+
+CREATE VIRTUAL TABLE source_template_fts USING fts5(
+  repo,
+  lang,
+  name,
+  branch,
+  subdir,
+  sha256_hash,
+  description,
+  version,
+  authors,
+  license,
+  keywords,
+  website,
+  repository,
+  content='source_template',
+  content_rowid='id'
+);
+
+-- after insert
+CREATE TRIGGER source_template_after_insert
+AFTER INSERT ON source_template
+BEGIN
+  INSERT INTO source_template_fts(rowid, repo, lang, name, branch, subdir, sha256_hash,
+    description, version, authors, license, keywords, website, repository)
+  VALUES (new.id, new.repo, new.lang, new.name, new.branch, new.subdir, new.sha256_hash,
+    new.description, new.version, new.authors, new.license, new.keywords, new.website, new.repository);
+END;
+
+-- after update
+CREATE TRIGGER source_template_after_update
+AFTER UPDATE ON source_template
+BEGIN
+  INSERT INTO source_template_fts(source_template_fts, rowid) VALUES('delete', old.id);
+
+  INSERT INTO source_template_fts(rowid, repo, lang, name, branch, subdir, sha256_hash,
+    description, version, authors, license, keywords, website, repository)
+  VALUES (new.id, new.repo, new.lang, new.name, new.branch, new.subdir, new.sha256_hash,
+    new.description, new.version, new.authors, new.license, new.keywords, new.website, new.repository);
+END;
+
+-- after delete
+CREATE TRIGGER source_template_after_delete
+AFTER DELETE ON source_template
+BEGIN
+  INSERT INTO source_template_fts(source_template_fts, rowid) VALUES('delete', old.id);
+END;
+
+INSERT INTO source_template_fts(source_template_fts) VALUES('rebuild');
@@ -0,0 +1,9 @@
+DROP TRIGGER IF EXISTS template_trigram_after_insert;
+DROP TRIGGER IF EXISTS template_trigram_after_update;
+DROP TRIGGER IF EXISTS template_trigram_after_delete;
+DROP TABLE IF EXISTS template_trigram;
+
+DROP TRIGGER IF EXISTS source_template_trigram_after_insert;
+DROP TRIGGER IF EXISTS source_template_trigram_after_update;
+DROP TRIGGER IF EXISTS source_template_trigram_after_delete;
+DROP TABLE IF EXISTS source_template_trigram;
@@ -0,0 +1,69 @@
+-- ------------------------------------------------ template_trigram
+
+-- Trigram indexes over names and keywords, used to suggest templates when a search finds nothing.
+
+CREATE VIRTUAL TABLE IF NOT EXISTS template_trigram USING fts5(
+  name,
+  keywords,
+  content='template',
+  content_rowid='id',
+  tokenize='trigram'
+);
+
+-- .................. template after insert
+CREATE TRIGGER IF NOT EXISTS template_trigram_after_insert AFTER INSERT ON template BEGIN
+    INSERT INTO template_trigram (rowid, name, keywords) VALUES (new.id, new.name, new.keywords);
+END;
+
+-- .................. template after update
+CREATE TRIGGER IF NOT EXISTS template_trigram_after_update AFTER UPDATE ON template BEGIN
+    INSERT INTO template_trigram (template_trigram, rowid, name, keywords)
+    VALUES ('delete', old.id, old.name, old.keywords);
+
+    INSERT INTO template_trigram (rowid, name, keywords) VALUES (new.id, new.name, new.keywords);
+END;
+
+-- .................. template after delete
+CREATE TRIGGER IF NOT EXISTS template_trigram_after_delete AFTER DELETE ON template BEGIN
+    INSERT INTO template_trigram (template_trigram, rowid, name, keywords)
+    VALUES ('delete', old.id, old.name, old.keywords);
+END;
+
+INSERT INTO template_trigram(template_trigram) VALUES('rebuild');
+
+-- ------------------------------------------------ source_template_trigram
+
+CREATE VIRTUAL TABLE IF NOT EXISTS source_template_trigram USING fts5(
+  name,
+  keywords,
+  content='source_template',
+  content_rowid='id',
+  tokenize='trigram'
+);
+
+-- after insert
+CREATE TRIGGER IF NOT EXISTS source_template_trigram_after_insert
+AFTER INSERT ON source_template
+BEGIN
+  INSERT INTO source_template_trigram(rowid, name, keywords) VALUES (new.id, new.name, new.keywords);
+END;
+
+-- after update
+CREATE TRIGGER IF NOT EXISTS source_template_trigram_after_update
+AFTER UPDATE ON source_template
+BEGIN
+  INSERT INTO source_template_trigram(source_template_trigram, rowid, name, keywords)
+  VALUES ('delete', old.id, old.name, old.keywords);
+
+  INSERT INTO source_template_trigram(rowid, name, keywords) VALUES (new.id, new.name, new.keywords);
+END;
+
+-- after delete
+CREATE TRIGGER IF NOT EXISTS source_template_trigram_after_delete
+AFTER DELETE ON source_template
+BEGIN
+  INSERT INTO source_template_trigram(source_template_trigram, rowid, name, keywords)
+  VALUES ('delete', old.id, old.name, old.keywords);
+END;
+
+INSERT INTO source_template_trigram(source_template_trigram) VALUES('rebuild');
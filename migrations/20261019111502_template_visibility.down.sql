@@ -0,0 +1,2 @@
+ALTER TABLE template DROP COLUMN visibility;
+ALTER TABLE source_template DROP COLUMN visibility;
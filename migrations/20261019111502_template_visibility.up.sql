@@ -0,0 +1,16 @@
+-- ------------------------------------------------ template/source_template: visibility
+
+-- `public` or `private`. Private templates stay out of `--public` listings and exports like
+-- `boil template pack`. Existing rows from local paths (no URL scheme or host) are private, hosts
+-- marked private in the config only apply from the next install.
+
+ALTER TABLE template ADD COLUMN visibility TEXT NOT NULL DEFAULT 'public';
+ALTER TABLE source_template ADD COLUMN visibility TEXT NOT NULL DEFAULT 'public';
+
+UPDATE template
+SET visibility = 'private'
+WHERE repo LIKE 'file://%' OR (instr(repo, '://') = 0 AND instr(repo, ':') = 0);
+
+UPDATE source_template
+SET visibility = 'private'
+WHERE repo LIKE 'file://%' OR (instr(repo, '://') = 0 AND instr(repo, ':') = 0);
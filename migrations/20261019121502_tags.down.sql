@@ -0,0 +1,5 @@
+ALTER TABLE template DROP COLUMN user_description;
+
+DROP TABLE IF EXISTS source_template_tag;
+DROP TABLE IF EXISTS template_tag;
+DROP TABLE IF EXISTS tag;
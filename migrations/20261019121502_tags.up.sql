@@ -0,0 +1,34 @@
+-- ------------------------------------------------ tag
+
+-- Tags are shared by installed and source templates. `origin` is `author` for the tags of
+-- boilermaker.toml, which are replaced on update, or `user` for `boil tag add`.
+
+CREATE TABLE IF NOT EXISTS tag (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS template_tag (
+  template_id INTEGER NOT NULL,
+  tag_id INTEGER NOT NULL,
+  origin TEXT NOT NULL DEFAULT 'user',
+  PRIMARY KEY (template_id, tag_id),
+  FOREIGN KEY (template_id) REFERENCES template(id) ON DELETE CASCADE,
+  FOREIGN KEY (tag_id) REFERENCES tag(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS source_template_tag (
+  source_template_id INTEGER NOT NULL,
+  tag_id INTEGER NOT NULL,
+  origin TEXT NOT NULL DEFAULT 'user',
+  PRIMARY KEY (source_template_id, tag_id),
+  FOREIGN KEY (source_template_id) REFERENCES source_template(id) ON DELETE CASCADE,
+  FOREIGN KEY (tag_id) REFERENCES tag(id) ON DELETE CASCADE
+);
+
+-- ------------------------------------------------ template: user description
+
+-- A description given at install (e.g. in the desktop app), kept apart from the `[project]`
+-- description so `boil update` doesn't overwrite it.
+
+ALTER TABLE template ADD COLUMN user_description TEXT;
@@ -0,0 +1,26 @@
+-- ------------------------------------------------ generation
+
+-- Every project made by `boil new`, to tell which template version and variables made it and to
+-- `boil regenerate` it. `template` is the name or <source>/<template> passed to `boil new`, or the
+-- URL or absolute path of the template, `template_hash` the hash of the template files at the time.
+-- Variables are a JSON object with secrets redacted.
+
+CREATE TABLE IF NOT EXISTS generation (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  template_id INTEGER,
+  template TEXT NOT NULL,
+  name TEXT NOT NULL,
+  lang TEXT NOT NULL,
+  repo TEXT,
+  branch TEXT,
+  subdir TEXT,
+  git_ref TEXT,
+  commit_sha TEXT,
+  template_hash TEXT,
+  project TEXT NOT NULL,
+  output_dir TEXT NOT NULL,
+  variables TEXT NOT NULL DEFAULT '{}',
+  created_at TIMESTAMP NOT NULL,
+  duration_ms INTEGER NOT NULL,
+  FOREIGN KEY (template_id) REFERENCES template(id) ON DELETE SET NULL
+);
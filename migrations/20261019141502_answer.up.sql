@@ -0,0 +1,12 @@
+-- ------------------------------------------------ answer
+
+-- The variable values last passed to `boil new` for a template, used in place of its defaults the
+-- next time. `template` is keyed like `generation.template`. Secrets are never stored.
+
+CREATE TABLE IF NOT EXISTS answer (
+  template TEXT NOT NULL,
+  name TEXT NOT NULL,
+  value TEXT NOT NULL,
+  updated_at TIMESTAMP NOT NULL,
+  PRIMARY KEY (template, name)
+);
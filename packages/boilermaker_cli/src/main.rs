@@ -11,6 +11,8 @@ use boilermaker_core::{
     db::LocalCache,
     logging,
     state::AppState,
+    template::auth::init_git_auth,
+    util::output::OutputFormat,
 };
 
 #[derive(Parser)]
@@ -32,25 +34,48 @@ struct Cli {
     )]
     debug: u8,
 
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = OutputFormat::Table,
+        help = "Output format, printed to stdout. Logs always go to stderr"
+    )]
+    format: OutputFormat,
+
     #[command(subcommand)]
     command: Option<Commands>,
 }
 
 #[derive(Subcommand)]
 enum Commands {
+    #[command(subcommand, about = "Manage the git mirror cache")]
+    Cache(commands::Cache),
+    #[command(about = "List and search the projects made with `boil new`")]
+    History(commands::History),
+    #[command(about = "Show details about a template")]
+    Info(commands::Info),
     #[command(about = "Install a template locally")]
     Install(commands::Install),
     #[command(about = "List all templates in the local cache")]
     List(commands::List),
     #[command(about = "Create a new project from a template")]
     New(commands::New),
+    #[command(about = "List installed templates that are behind their remote")]
+    Outdated(commands::Outdated),
+    #[command(about = "Make a project from the history again")]
+    Regenerate(commands::Regenerate),
     #[command(about = "Remove a template from the local cache")]
     Remove(commands::Remove),
     #[command(about = "Search for templates")]
     Search(commands::Search),
     #[command(subcommand, about = "Manage Sources")]
     Sources(commands::Sources),
-    #[command(about = "Update an installed template")]
+    #[command(subcommand, about = "Manage template tags")]
+    Tag(commands::Tag),
+    #[command(subcommand, about = "Work on templates (pack)")]
+    Template(commands::Template),
+    #[command(about = "Update installed templates (by ID, name, source or --all)")]
     Update(commands::Update),
 }
 
@@ -72,24 +97,41 @@ async fn main() -> Result<()> {
         sys_config: get_system_config(cli.config.as_deref())?,
         log_level: cli.debug,
         local_db: Arc::new(LocalCache::new(cache_path).await?),
+        output_format: cli.format,
     };
 
-    let cache = app_state.local_db.clone();
-    if !cache.template_table_exists().await? {
-        cache.create_schema().await?;
-    }
+    init_git_auth(app_state.sys_config.git.as_ref());
+
+    // Migrations are idempotent, so always run them to bring older caches up to date.
+    app_state.local_db.create_schema().await?;
 
     if let Some(command) = cli.command {
         match command {
+            Commands::Cache(subcmd) => match subcmd {
+                commands::Cache::List(cmd) => commands::cache::list(&app_state, &cmd).await?,
+                commands::Cache::Prune(cmd) => commands::cache::prune(&app_state, &cmd).await?,
+            },
+            Commands::History(cmd) => commands::history(&app_state, &cmd).await?,
+            Commands::Info(cmd) => commands::info(&app_state, &cmd).await?,
             Commands::Install(cmd) => commands::install(&app_state, &cmd).await?,
             Commands::List(cmd) => commands::list(&app_state, &cmd).await?,
             Commands::New(cmd) => commands::new(&app_state, &cmd).await?,
+            Commands::Outdated(cmd) => commands::outdated(&app_state, &cmd).await?,
+            Commands::Regenerate(cmd) => commands::regenerate(&app_state, &cmd).await?,
             Commands::Remove(cmd) => commands::remove(&app_state, &cmd).await?,
             Commands::Search(cmd) => commands::search(&app_state, &cmd).await?,
             Commands::Sources(subcmd) => match subcmd {
                 commands::Sources::Add(cmd) => commands::sources::add(&app_state, &cmd).await?,
                 commands::Sources::List(cmd) => commands::sources::list(&app_state, &cmd).await?,
             },
+            Commands::Tag(subcmd) => match subcmd {
+                commands::Tag::Add(cmd) => commands::tag::add(&app_state, &cmd).await?,
+                commands::Tag::Remove(cmd) => commands::tag::remove(&app_state, &cmd).await?,
+                commands::Tag::List(cmd) => commands::tag::list(&app_state, &cmd).await?,
+            },
+            Commands::Template(subcmd) => match subcmd {
+                commands::Template::Pack(cmd) => commands::template::pack(&app_state, &cmd).await?,
+            },
             Commands::Update(cmd) => commands::update(&app_state, &cmd).await?,
         }
     } else {
@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+use std::time::UNIX_EPOCH;
+
+use clap::{Parser, Subcommand};
+use color_eyre::Result;
+use serde::Serialize;
+use tabled::{Table, Tabled, settings::Style};
+use tracing::info;
+
+use crate::db::TemplateFindParams;
+use crate::state::AppState;
+use crate::template::mirror::{MirrorInfo, list_mirrors, mirror_key, remove_mirror};
+use crate::util::output::print_serialized;
+use crate::util::time::timestamp_to_iso8601;
+
+#[derive(Subcommand)]
+pub enum Cache {
+    #[command(about = "List git mirrors and their sizes")]
+    List(List),
+    #[command(about = "Remove git mirrors")]
+    Prune(Prune),
+}
+
+#[derive(Debug, Parser)]
+pub struct List {}
+
+#[derive(Debug, Parser)]
+pub struct Prune {
+    #[arg(
+        short,
+        long,
+        help = "Remove all mirrors, not just the ones no installed template uses"
+    )]
+    pub all: bool,
+}
+
+// A mirror, printed with `--format json|yaml|csv`.
+#[derive(Debug, Serialize)]
+pub struct MirrorResult {
+    pub key: String,
+    pub url: String,
+    pub path: String,
+    pub size_bytes: u64,
+    pub fetched_at: Option<String>,
+}
+
+impl MirrorResult {
+    pub fn from(m: &MirrorInfo) -> Self {
+        Self {
+            key: m.key.to_owned(),
+            url: m.url.to_owned(),
+            path: m.path.display().to_string(),
+            size_bytes: m.size_bytes,
+            fetched_at: fetched_at(m),
+        }
+    }
+}
+
+#[derive(Debug, Tabled)]
+pub struct TabledMirror {
+    pub url: String,
+    pub size: String,
+    pub fetched_at: String,
+    pub key: String,
+}
+
+impl TabledMirror {
+    pub fn from(m: &MirrorInfo) -> Self {
+        Self {
+            url: m.url.to_owned(),
+            size: format_size(m.size_bytes),
+            fetched_at: fetched_at(m).unwrap_or_else(|| "-".to_owned()),
+            key: m.key.to_owned(),
+        }
+    }
+}
+
+fn fetched_at(m: &MirrorInfo) -> Option<String> {
+    m.modified_at
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| timestamp_to_iso8601(d.as_secs() as i64))
+}
+
+#[tracing::instrument]
+pub async fn list(app_state: &AppState, _cmd: &List) -> Result<()> {
+    let mirrors = list_mirrors()?;
+    if !app_state.output_format.is_table() {
+        let rows = mirrors.iter().map(MirrorResult::from).collect::<Vec<_>>();
+        return print_serialized(app_state.output_format, &rows);
+    }
+    if mirrors.is_empty() {
+        info!("No git mirrors cached.");
+        return Ok(());
+    }
+
+    let total: u64 = mirrors.iter().map(|m| m.size_bytes).sum();
+    let rows = mirrors.iter().map(TabledMirror::from).collect::<Vec<_>>();
+    let mut table = Table::new(&rows);
+    table.with(Style::psql());
+    print!("\n\n{table}\n\n");
+
+    info!("{} mirror(s), {} total", mirrors.len(), format_size(total));
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn prune(app_state: &AppState, cmd: &Prune) -> Result<()> {
+    let in_use: HashSet<String> = if cmd.all {
+        HashSet::new()
+    } else {
+        app_state
+            .local_db
+            .find_templates(TemplateFindParams::default())
+            .await?
+            .iter()
+            .map(|t| mirror_key(&t.repo))
+            .collect()
+    };
+
+    let mut freed = 0;
+    let mut removed = 0;
+    for mirror in list_mirrors()? {
+        if in_use.contains(&mirror.key) {
+            continue;
+        }
+        info!(
+            "Removing mirror: {} ({})",
+            mirror.url,
+            format_size(mirror.size_bytes)
+        );
+        remove_mirror(&mirror)?;
+        freed += mirror.size_bytes;
+        removed += 1;
+    }
+
+    info!(
+        "✅ Removed {} mirror(s), freed {}",
+        removed,
+        format_size(freed)
+    );
+    Ok(())
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
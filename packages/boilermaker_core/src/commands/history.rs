@@ -0,0 +1,266 @@
+use std::time::Instant;
+
+use clap::{Parser, Subcommand};
+use color_eyre::{Result, eyre::eyre};
+use tabled::{Table, Tabled, settings::Style};
+use tracing::{info, warn};
+
+use crate::commands::new::{
+    New, is_template_location, record_generation, select_template, vec_to_hashmap, write_project,
+};
+use crate::db::{
+    GenerationResult, ListGenerationOptions, REDACTED, TabledGenerationResult, format_duration_ms,
+};
+use crate::state::AppState;
+use crate::template::archive::TemplateLocation;
+use crate::template::fs::sha256_hash_fs;
+use crate::util::output::print_serialized;
+use crate::util::time::timestamp_to_iso8601;
+
+#[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct History {
+    #[command(subcommand)]
+    pub command: Option<HistoryCommand>,
+    #[arg(help = "Only list projects whose name, path, template or variables contain this")]
+    pub query: Option<String>,
+    #[arg(short, long, help = "Only list projects made from this template")]
+    pub template: Option<String>,
+    #[arg(short, long, help = "Only list projects made for this language")]
+    pub lang: Option<String>,
+    #[arg(short = 'n', long, help = "Maximum number of projects to list")]
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HistoryCommand {
+    #[command(about = "Show how a project was generated")]
+    Show(Show),
+}
+
+#[derive(Debug, Parser)]
+pub struct Show {
+    #[arg(required = true, help = "Generation ID")]
+    pub id: i64,
+}
+
+#[derive(Debug, Parser)]
+pub struct Regenerate {
+    #[arg(required = true, help = "Generation ID, see `boil history`")]
+    pub id: i64,
+    #[arg(
+        short,
+        long,
+        help = "Directory to create the project in (default: current dir)"
+    )]
+    pub dir: Option<String>,
+    #[arg(short = 'O', long, default_value_t = false)]
+    pub overwrite: bool,
+    #[arg(
+        short = 'v',
+        long = "var",
+        value_name = "KEY=VALUE",
+        help = "Values for the redacted variables"
+    )]
+    pub vars: Vec<String>,
+}
+
+#[derive(Debug, Tabled)]
+struct FieldRow {
+    #[tabled(rename = "Field")]
+    field: String,
+    #[tabled(rename = "Value")]
+    value: String,
+}
+
+#[derive(Debug, Tabled)]
+struct VariableRow {
+    #[tabled(rename = "Variable")]
+    name: String,
+    #[tabled(rename = "Value")]
+    value: String,
+}
+
+#[tracing::instrument]
+pub async fn history(app_state: &AppState, cmd: &History) -> Result<()> {
+    if let Some(HistoryCommand::Show(show_cmd)) = &cmd.command {
+        return show(app_state, show_cmd).await;
+    }
+
+    let filtered = cmd.query.is_some() || cmd.template.is_some() || cmd.lang.is_some();
+    let opts = ListGenerationOptions {
+        query: cmd.query.clone(),
+        name: cmd.template.clone(),
+        lang: cmd.lang.clone(),
+        limit: cmd.limit,
+    };
+    let generations = app_state.local_db.list_generations(opts).await?;
+
+    if !app_state.output_format.is_table() {
+        return print_serialized(app_state.output_format, &generations);
+    }
+
+    if generations.is_empty() && filtered {
+        info!("No matching projects.");
+        return Ok(());
+    }
+    if generations.is_empty() {
+        info!("No projects generated yet.");
+        info!("💡 Have a look at `boil new`");
+        return Ok(());
+    }
+
+    let rows = generations
+        .into_iter()
+        .map(TabledGenerationResult::from)
+        .collect::<Vec<_>>();
+    let mut table = Table::new(&rows);
+    table.with(Style::psql());
+    print!("\n\n{table}\n\n");
+
+    Ok(())
+}
+
+async fn show(app_state: &AppState, cmd: &Show) -> Result<()> {
+    let generation = get_generation(app_state, cmd.id).await?;
+
+    if !app_state.output_format.is_table() {
+        return print_serialized(app_state.output_format, &generation);
+    }
+
+    let variables = generation
+        .variables()?
+        .into_iter()
+        .map(|(name, value)| VariableRow { name, value })
+        .collect::<Vec<_>>();
+
+    let mut table = Table::new(generation_rows(generation));
+    table.with(Style::psql());
+    print!("\n📜 Generation\n\n{table}\n");
+
+    let mut table = Table::new(&variables);
+    table.with(Style::psql());
+    print!("\n🔣 Variables\n\n{table}\n\n");
+
+    Ok(())
+}
+
+// Make the project of a generation again, with the same template files and variables. Git
+// templates are fetched at the recorded commit, others are used as they are now.
+#[tracing::instrument]
+pub async fn regenerate(app_state: &AppState, cmd: &Regenerate) -> Result<()> {
+    let started = Instant::now();
+    let generation = get_generation(app_state, cmd.id).await?;
+
+    let mut variables = generation.variables()?;
+    for (name, value) in vec_to_hashmap(&cmd.vars)? {
+        if !variables.contains_key(&name) {
+            return Err(eyre!(
+                "💥 `{}` isn't a variable of generation #{}.",
+                name,
+                generation.id
+            ));
+        }
+        variables.insert(name, value);
+    }
+    let redacted = variables
+        .iter()
+        .filter(|(_, value)| value.as_str() == REDACTED)
+        .map(|(name, _)| format!("--var {name}=..."))
+        .collect::<Vec<_>>();
+    if !redacted.is_empty() {
+        return Err(eyre!(
+            "💥 Secrets aren't kept in the history. Pass them again with: {}",
+            redacted.join(" ")
+        ));
+    }
+
+    let (name, subdir) = match (&generation.repo, &generation.commit_sha) {
+        (Some(repo), Some(sha)) if TemplateLocation::detect(repo).is_git() => {
+            (format!("{repo}@{sha}"), generation.subdir.clone())
+        }
+        _ if is_template_location(&generation.template) => {
+            (generation.template.clone(), generation.subdir.clone())
+        }
+        _ => (generation.template.clone(), None),
+    };
+    let new_cmd = New {
+        name,
+        project: None,
+        lang: Some(generation.lang.clone()),
+        rename: Some(generation.project.clone()),
+        dir: cmd.dir.clone(),
+        output_path: None,
+        overwrite: cmd.overwrite,
+        vars: Vec::new(),
+        branch: None,
+        subdir,
+        fresh: true,
+        preset: None,
+    };
+    let Some(t) = select_template(app_state, &new_cmd).await? else {
+        return Ok(());
+    };
+
+    if let Some(hash) = &generation.template_hash
+        && &sha256_hash_fs(t.fs.as_ref())? != hash
+    {
+        warn!(
+            "The files of {} changed since generation #{}, the project may differ.",
+            generation.template, generation.id
+        );
+    }
+
+    let report = write_project(
+        &t,
+        &generation.project,
+        cmd.dir.as_deref(),
+        cmd.overwrite,
+        variables.into_iter().collect(),
+    )
+    .await?;
+    record_generation(app_state, &generation.template, &t, &report, started).await;
+    if !app_state.output_format.is_table() {
+        print_serialized(app_state.output_format, &report)?;
+    }
+
+    Ok(())
+}
+
+async fn get_generation(app_state: &AppState, id: i64) -> Result<GenerationResult> {
+    match app_state.local_db.get_generation(id).await? {
+        Some(generation) => Ok(generation),
+        None => Err(eyre!("💥 No generation found with ID: {}", id)),
+    }
+}
+
+fn generation_rows(generation: GenerationResult) -> Vec<FieldRow> {
+    [
+        ("ID", Some(generation.id.to_string())),
+        ("Project", Some(generation.project)),
+        ("Output dir", Some(generation.output_dir)),
+        ("Template", Some(generation.template)),
+        (
+            "Template ID",
+            generation.template_id.map(|id| id.to_string()),
+        ),
+        ("Lang", Some(generation.lang)),
+        ("Repo", generation.repo),
+        ("Branch", generation.branch),
+        ("Subdirectory", generation.subdir),
+        ("Ref", generation.git_ref),
+        ("Commit", generation.commit_sha),
+        ("Template hash", generation.template_hash),
+        (
+            "Created at",
+            Some(timestamp_to_iso8601(generation.created_at)),
+        ),
+        ("Duration", Some(format_duration_ms(generation.duration_ms))),
+    ]
+    .into_iter()
+    .map(|(field, value)| FieldRow {
+        field: field.to_owned(),
+        value: value.unwrap_or_else(|| "-".to_owned()),
+    })
+    .collect()
+}
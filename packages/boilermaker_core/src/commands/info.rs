@@ -0,0 +1,368 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use clap::Parser;
+use color_eyre::{Result, eyre::eyre};
+use serde::Serialize;
+use tabled::{Table, Tabled, settings::Style};
+
+use crate::commands::new::open_source_template;
+use crate::config::{TemplateConfig, TemplateConfigProject};
+use crate::db::{TabledTemplateListResult, TemplateFindParams, TemplateResult};
+use crate::state::AppState;
+use crate::template::fs::{DirFs, TemplateFs, read_template_config, template_langs};
+use crate::template::static_analysis::find_variables_in_fs;
+use crate::util::output::print_serialized;
+
+#[derive(Debug, Parser)]
+pub struct Info {
+    #[arg(required = true, help = "Template ID, name, or <source>/<template>")]
+    pub template: String,
+    #[arg(short, long, help = "Only show the given language")]
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Tabled)]
+struct FieldRow {
+    #[tabled(rename = "Field")]
+    field: String,
+    #[tabled(rename = "Value")]
+    value: String,
+}
+
+#[derive(Debug, Serialize, Tabled)]
+struct SourceTemplateRow {
+    id: i64,
+    source: String,
+    name: String,
+    lang: String,
+    repo: String,
+    branch: String,
+    subdir: String,
+}
+
+#[derive(Debug, Serialize, Tabled)]
+struct VariableRow {
+    #[tabled(rename = "Variable")]
+    name: String,
+    #[tabled(rename = "Default")]
+    default: String,
+    #[tabled(rename = "Description")]
+    description: String,
+    #[tabled(rename = "Status")]
+    status: String,
+}
+
+// Where the template was found.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum TemplateOrigin {
+    Installed(Vec<TemplateResult>),
+    Source(Vec<SourceTemplateRow>),
+}
+
+#[derive(Debug, Serialize)]
+struct TemplateFile {
+    path: String,
+    // Jinja renders something in it.
+    templated: bool,
+    binary: bool,
+}
+
+// Everything `boil info` shows, printed with `--format json|yaml|csv`.
+#[derive(Debug, Serialize)]
+struct TemplateInfo<'a> {
+    #[serde(flatten)]
+    project: &'a TemplateConfigProject,
+    languages: &'a [String],
+    presets: Vec<String>,
+    #[serde(flatten)]
+    origin: TemplateOrigin,
+    variables: Vec<VariableRow>,
+    files: Vec<TemplateFile>,
+}
+
+#[tracing::instrument]
+pub async fn info(app_state: &AppState, cmd: &Info) -> Result<()> {
+    let (template_fs, origin) = match cmd.template.split_once('/') {
+        Some((source_name, name)) => {
+            find_source_template(app_state, cmd, source_name, name).await?
+        }
+        None => find_installed_template(app_state, cmd).await?,
+    };
+
+    let config = read_template_config(template_fs.as_ref())?;
+    let mut langs = template_langs(template_fs.as_ref())?;
+    if let Some(lang) = &cmd.lang {
+        if !langs.contains(lang) {
+            return Err(eyre!(
+                "💥 Template has no `{lang}` directory. Available: {}.",
+                langs.join(", ")
+            ));
+        }
+        langs = vec![lang.to_owned()];
+    }
+
+    let variables = variable_rows(&config, template_fs.as_ref(), &langs)?;
+    let files = template_files(template_fs.as_ref(), &langs)?;
+
+    if !app_state.output_format.is_table() {
+        let info = TemplateInfo {
+            project: &config.project,
+            languages: &langs,
+            presets: config.preset_names(),
+            origin,
+            variables,
+            files,
+        };
+        return print_serialized(app_state.output_format, &info);
+    }
+
+    let mut table = Table::new(project_rows(&config, &langs));
+    table.with(Style::psql());
+    print!("\n📦 Project\n\n{table}\n");
+
+    print!("\n{}\n", origin_tables(&origin));
+
+    let mut table = Table::new(&variables);
+    table.with(Style::psql());
+    print!("\n🔣 Variables\n\n{table}\n");
+
+    let tree = file_tree(&files);
+    print!("\n🗂️  Files (* = templated)\n\n{tree}\n");
+
+    Ok(())
+}
+
+// An installed template by ID or name, with its files and installed langs.
+async fn find_installed_template(
+    app_state: &AppState,
+    cmd: &Info,
+) -> Result<(Box<dyn TemplateFs>, TemplateOrigin)> {
+    let cache = app_state.local_db.clone();
+
+    let name = match cmd.template.parse::<i64>() {
+        Ok(id) => match cache.get_template(id).await? {
+            Some(t) => t.name,
+            None => return Err(eyre!("💥 Cannot find template: {}.", id)),
+        },
+        Err(_) => cmd.template.to_owned(),
+    };
+    let find_params = TemplateFindParams {
+        name: Some(name.to_owned()),
+        lang: cmd.lang.clone(),
+        ..Default::default()
+    };
+    let templates: Vec<TemplateResult> = cache.find_templates(find_params).await?;
+    let Some(first) = templates.first() else {
+        return Err(eyre!("💥 Cannot find template: {}.", cmd.template));
+    };
+
+    let template_fs = Box::new(DirFs::new(&first.template_dir));
+    Ok((template_fs, TemplateOrigin::Installed(templates)))
+}
+
+// A template from an added source, read from the source index (or its repo).
+async fn find_source_template(
+    app_state: &AppState,
+    cmd: &Info,
+    source_name: &str,
+    name: &str,
+) -> Result<(Box<dyn TemplateFs>, TemplateOrigin)> {
+    let templates = app_state
+        .local_db
+        .find_source_templates(source_name, name, cmd.lang.clone())
+        .await?;
+    let Some(first) = templates.first() else {
+        return Err(eyre!(
+            "💥 Cannot find template `{}` in source `{}`.",
+            name,
+            source_name
+        ));
+    };
+
+    let template_fs = open_source_template(app_state, first).await?;
+    let rows = templates
+        .iter()
+        .map(|t| SourceTemplateRow {
+            id: t.id,
+            source: t.source_name.to_owned(),
+            name: t.name.to_owned(),
+            lang: t.lang.to_owned(),
+            repo: t.repo.to_owned(),
+            branch: t.branch.clone().unwrap_or_else(|| "-".to_owned()),
+            subdir: t.subdir.clone().unwrap_or_else(|| "-".to_owned()),
+        })
+        .collect::<Vec<_>>();
+
+    Ok((template_fs, TemplateOrigin::Source(rows)))
+}
+
+fn origin_tables(origin: &TemplateOrigin) -> String {
+    match origin {
+        TemplateOrigin::Installed(templates) => {
+            let location = templates
+                .first()
+                .map(|t| format!("\n\n📁 {}", t.template_dir))
+                .unwrap_or_default();
+            let rows = templates
+                .iter()
+                .cloned()
+                .map(TabledTemplateListResult::from)
+                .collect::<Vec<_>>();
+            let mut table = Table::new(&rows);
+            table.with(Style::psql());
+            format!("📥 Installed\n\n{table}{location}")
+        }
+        TemplateOrigin::Source(rows) => {
+            let mut table = Table::new(rows);
+            table.with(Style::psql());
+            format!("🌐 From source\n\n{table}")
+        }
+    }
+}
+
+fn project_rows(config: &TemplateConfig, langs: &[String]) -> Vec<FieldRow> {
+    let project = &config.project;
+    let list = |items: &Option<Vec<String>>| items.as_ref().map(|items| items.join(", "));
+    [
+        ("Name", project.name.clone()),
+        ("Version", project.version.clone()),
+        ("Description", project.description.clone()),
+        ("Authors", list(&project.authors)),
+        ("License", project.license.clone()),
+        ("Keywords", list(&project.keywords)),
+        ("Tags", list(&project.tags)),
+        ("Presets", Some(config.preset_names().join(", "))),
+        ("Website", project.website.clone()),
+        ("Default lang", project.default_lang.clone()),
+        ("Languages", Some(langs.join(", "))),
+    ]
+    .into_iter()
+    .map(|(field, value)| FieldRow {
+        field: field.to_owned(),
+        value: value
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "-".to_owned()),
+    })
+    .collect()
+}
+
+// Declared variables, plus the ones the files use without declaring them.
+fn variable_rows(
+    config: &TemplateConfig,
+    template_fs: &dyn TemplateFs,
+    langs: &[String],
+) -> Result<Vec<VariableRow>> {
+    let mut used = BTreeSet::new();
+    for lang in langs {
+        used.extend(find_variables_in_fs(template_fs, Some(Path::new(lang)))?);
+    }
+
+    let declared = config
+        .variables
+        .as_ref()
+        .map(|vars| vars.as_map().iter().collect::<BTreeMap<_, _>>())
+        .unwrap_or_default();
+
+    let mut rows = Vec::new();
+    for (name, default) in &declared {
+        rows.push(VariableRow {
+            name: name.to_string(),
+            default: default.to_string(),
+            description: config
+                .variables
+                .as_ref()
+                .and_then(|vars| vars.description(name))
+                .unwrap_or("-")
+                .to_owned(),
+            status: if used.contains(*name) {
+                "declared".to_owned()
+            } else {
+                "declared, unused".to_owned()
+            },
+        });
+    }
+    for name in used.iter().filter(|v| !declared.contains_key(v)) {
+        rows.push(VariableRow {
+            name: name.to_owned(),
+            default: "-".to_owned(),
+            description: "-".to_owned(),
+            status: "undeclared".to_owned(),
+        });
+    }
+
+    Ok(rows)
+}
+
+#[derive(Debug, Default)]
+struct TreeNode {
+    children: BTreeMap<String, TreeNode>,
+    // Only set for files.
+    marker: Option<String>,
+}
+
+// The template files of `langs` and the top-level files.
+fn template_files(template_fs: &dyn TemplateFs, langs: &[String]) -> Result<Vec<TemplateFile>> {
+    let mut files = Vec::new();
+    for path in template_fs.list_files()? {
+        let is_top_level = path.components().count() == 1;
+        if !is_top_level && !langs.iter().any(|lang| path.starts_with(lang)) {
+            continue;
+        }
+
+        let content = template_fs.read_text(&path)?;
+        files.push(TemplateFile {
+            path: path.to_string_lossy().to_string(),
+            templated: content
+                .as_ref()
+                .is_some_and(|c| c.contains("{{") || c.contains("{%")),
+            binary: content.is_none(),
+        });
+    }
+    Ok(files)
+}
+
+// The files drawn like `tree` does. Files Jinja renders something in are marked with `*`.
+fn file_tree(files: &[TemplateFile]) -> String {
+    let mut root = TreeNode::default();
+    for file in files {
+        let marker = match file {
+            TemplateFile { binary: true, .. } => " (binary)",
+            TemplateFile {
+                templated: true, ..
+            } => " *",
+            _ => "",
+        };
+
+        let mut node = &mut root;
+        for component in Path::new(&file.path).components() {
+            let part = component.as_os_str().to_string_lossy().to_string();
+            node = node.children.entry(part).or_default();
+        }
+        node.marker = Some(marker.to_owned());
+    }
+
+    let mut out = String::new();
+    draw_tree(&root, "", &mut out);
+    out
+}
+
+fn draw_tree(node: &TreeNode, prefix: &str, out: &mut String) {
+    let count = node.children.len();
+    for (i, (name, child)) in node.children.iter().enumerate() {
+        let last = i + 1 == count;
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        match &child.marker {
+            Some(marker) => out.push_str(&format!("{prefix}{branch}{name}{marker}\n")),
+            None => {
+                out.push_str(&format!("{prefix}{branch}{name}/\n"));
+                draw_tree(child, &format!("{prefix}{indent}"), out);
+            }
+        }
+    }
+}
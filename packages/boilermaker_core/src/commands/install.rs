@@ -1,68 +1,93 @@
+use std::path::Path;
+
 use clap::Parser;
 use color_eyre::{Result, eyre::eyre};
 use tracing::{error, info};
 
-use crate::db::TemplateRow;
+use crate::config::TemplateConfig;
+use crate::db::{
+    TagOrigin, TemplateFindParams, TemplateMetadata, TemplateResult, TemplateRow, Visibility,
+};
 use crate::state::AppState;
+use crate::template::archive::{OpenedTemplate, TemplateLocation, open_template};
+use crate::template::fs::{TemplateFs, read_template_config, template_langs};
+use crate::template::pack::is_file_name_safe;
 use crate::template::{
-    CloneContext, clean_dir, clone_repo, get_lang, get_template_config, get_template_dir_path,
-    install_template, make_name_from_url, make_tmp_dir_from_url,
+    CloneContext, get_template_dir_path, install_template, make_name_from_url, split_repo_ref,
 };
-use crate::util::file::remove_git_dir;
+use crate::util::output::print_serialized;
 
 #[derive(Debug, Parser)]
 pub struct Install {
-    #[arg(required = true)]
+    #[arg(
+        required = true,
+        help = "Template repo (optionally pinned with @<tag|commit|branch>), local dir, or .tar.gz/.zip file or URL"
+    )]
     pub template: String,
     #[arg(short, long)]
     pub name: Option<String>,
-    #[arg(short, long)]
-    pub lang: Option<String>,
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        help = "Only install these languages (default: every language dir of the template)"
+    )]
+    pub lang: Vec<String>,
     #[arg(short, long)]
     pub branch: Option<String>,
     #[arg(short = 'd', long)]
     pub subdir: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        help = "Visibility of the template (default: private for local paths and private hosts)"
+    )]
+    pub visibility: Option<Visibility>,
+    #[arg(long, help = "Your own description, shown instead of the template's")]
+    pub description: Option<String>,
+    #[arg(
+        short,
+        long = "tag",
+        value_name = "TAG",
+        value_delimiter = ',',
+        help = "Tag the template, on top of the tags its author gave it"
+    )]
+    pub tags: Vec<String>,
 }
 
 #[tracing::instrument]
 pub async fn install(app_state: &AppState, cmd: &Install) -> Result<()> {
-    let name = if let Some(name) = &cmd.name {
-        name.to_owned()
-    } else {
-        make_name_from_url(&cmd.template)
-    };
-
-    let repo_ctx = CloneContext::from(cmd);
-    let clone_dir = repo_ctx.dest.as_ref().unwrap();
-
-    if let Err(err) = clean_dir(clone_dir) {
-        return Err(eyre!("💥 Failed setting up clone dir: {}", err));
+    let (repo, git_ref) = split_repo_ref(&cmd.template);
+    if git_ref.is_some() && cmd.branch.is_some() {
+        return Err(eyre!(
+            "💥 Use either `<repo>@<ref>` or `--branch`, not both."
+        ));
     }
 
-    info!("Cloning template");
-    if let Err(err) = clone_repo(&repo_ctx).await {
-        return Err(eyre!("💥 Failed to clone template: {}", err));
+    let location = TemplateLocation::detect(&repo);
+    if !location.is_git() && (git_ref.is_some() || cmd.branch.is_some()) {
+        return Err(eyre!("💥 `@<ref>` and `--branch` only apply to git repos."));
     }
 
-    let work_dir = if let Some(subdir) = &cmd.subdir {
-        clone_dir.join(subdir)
-    } else {
-        clone_dir.to_path_buf()
+    let repo_ctx = CloneContext::from(cmd);
+    let opened = match open_template(&location, &repo_ctx).await {
+        Ok(opened) => opened,
+        Err(err) => return Err(eyre!("💥 Failed to fetch template: {}", err)),
     };
 
-    let cnf = get_template_config(work_dir.as_path())?;
-    let lang = get_lang(&cnf, &cmd.lang)?;
-    let template_dir = get_template_dir_path(&name)?;
-    let row = TemplateRow {
-        name,
-        lang,
-        template_dir: template_dir.to_str().unwrap().to_string(),
-        repo: cmd.template.to_owned(),
-        branch: cmd.branch.to_owned(),
-        subdir: cmd.subdir.to_owned(),
-        sha256_hash: None,
+    let config = read_template_config(opened.fs.as_ref())?;
+    let name = match &cmd.name {
+        Some(name) => name.to_owned(),
+        None => default_name(&location, &repo, &opened, &config),
     };
-    let row = row.set_hash_string();
+    let langs = select_langs(opened.fs.as_ref(), &cmd.lang)?;
+    let template_dir = get_template_dir_path(&name)?;
+    let repo = location.to_repo_string()?;
+
+    let visibility = cmd.visibility.unwrap_or_else(|| Visibility::infer(&repo));
+    if visibility == Visibility::Private {
+        info!("Installing {name} as a private template.");
+    }
 
     let cache = app_state.local_db.clone();
 
@@ -70,53 +95,203 @@ pub async fn install(app_state: &AppState, cmd: &Install) -> Result<()> {
         cache.create_schema().await?;
     }
 
-    let existing_db_entry = cache.check_unique(&row).await?;
+    let mut rows = Vec::new();
+    for lang in &langs {
+        let row = TemplateRow {
+            name: name.to_owned(),
+            lang: lang.to_owned(),
+            template_dir: template_dir.to_str().unwrap().to_string(),
+            repo: repo.to_owned(),
+            branch: cmd.branch.to_owned(),
+            subdir: cmd.subdir.to_owned(),
+            sha256_hash: None,
+            git_ref: opened.cloned.as_ref().map(|c| c.git_ref.to_owned()),
+            commit_sha: opened.commit_sha(),
+            pinned: opened
+                .cloned
+                .as_ref()
+                .is_some_and(|c| c.ref_kind.is_pinned()),
+            metadata: TemplateMetadata::from(&config.project),
+            visibility,
+            user_description: cmd.description.clone(),
+        };
+        let row = row.set_hash_string();
 
-    if let Some(t) = existing_db_entry {
-        if template_dir.exists() {
-            error!(
-                "💥 Template with the same name/lang/repo already exists: {}, {}, {}",
-                t.name, t.lang, t.repo
-            );
-            return Ok(());
-        } else {
+        if let Some(t) = cache.check_unique(&row).await? {
+            if template_dir.exists() {
+                info!("Template is already installed: {} ({})", t.name, t.lang);
+                continue;
+            }
             info!(
-                "Template entry exists in DB but directory is missing. Reininstalling: {}.",
-                t.name
+                "Template entry exists in DB but directory is missing. Reininstalling: {} ({}).",
+                t.name, t.lang
             );
             cache.delete_template(t.id).await?;
         }
+        rows.push(row);
     }
 
-    let new_id = cache.create_template(row).await?;
-
-    info!("Template added to cache with ID: {}", new_id);
+    if rows.is_empty() {
+        error!(
+            "💥 Template with the same name/lang/repo already exists: {}, {}, {}",
+            name,
+            langs.join(", "),
+            repo
+        );
+        if !app_state.output_format.is_table() {
+            print_serialized(app_state.output_format, &Vec::<TemplateResult>::new())?;
+        }
+        return Ok(());
+    }
 
-    match install_template(&work_dir, &template_dir).await {
-        Ok(_) => info!(
-            "Template installed successfully to: {}",
-            template_dir.display()
-        ),
-        Err(e) => {
-            return Err(eyre!("💥 Failed to install template: {}", e));
+    let installed = find_installed_langs(app_state, &name, &repo, &template_dir).await?;
+    if let Some(sibling) = installed.first() {
+        // The dir already holds every lang of the repo, at the commit the other langs are on.
+        for row in rows.iter_mut() {
+            if !template_dir.join(&row.lang).is_dir() {
+                return Err(eyre!(
+                    "💥 `{}` isn't in the installed copy of {}. Run `boil update {}` first.",
+                    row.lang,
+                    name,
+                    name
+                ));
+            }
+            row.git_ref = sibling.git_ref.clone();
+            row.commit_sha = sibling.commit_sha.clone();
+            row.pinned = sibling.pinned;
+        }
+        info!(
+            "Sharing {} with the installed lang(s): {}",
+            template_dir.display(),
+            installed
+                .iter()
+                .map(|t| t.lang.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    } else {
+        match install_template(opened.fs.as_ref(), &template_dir).await {
+            Ok(_) => info!(
+                "Template installed successfully to: {}",
+                template_dir.display()
+            ),
+            Err(e) => {
+                return Err(eyre!("💥 Failed to install template: {}", e));
+            }
         }
     }
 
-    cache.index_template(new_id).await?;
+    let author_tags = config.project.tags.clone().unwrap_or_default();
+    let mut new_ids = Vec::new();
+    for row in rows {
+        let lang = row.lang.to_owned();
+        let new_id = cache.create_template(row).await?;
+        info!("Template added to cache with ID: {} ({})", new_id, lang);
+
+        // User tags first, so they stay when the author drops the same tag.
+        cache
+            .add_template_tags(new_id, &cmd.tags, TagOrigin::User)
+            .await?;
+        cache.set_author_template_tags(new_id, &author_tags).await?;
+
+        cache.index_template(new_id).await?;
+        new_ids.push(new_id);
+    }
     info!("Template indexed successfully.");
 
-    remove_git_dir(&template_dir)?;
+    if !app_state.output_format.is_table() {
+        let mut templates = Vec::new();
+        for id in new_ids {
+            templates.extend(cache.get_template(id).await?);
+        }
+        print_serialized(app_state.output_format, &templates)?;
+    }
 
     Ok(())
 }
 
+// Archive names carry the version (`demo-1.0.0.tar.gz`), so archives are named after the template
+// instead, when it has a usable name.
+fn default_name(
+    location: &TemplateLocation,
+    repo: &str,
+    opened: &OpenedTemplate,
+    config: &TemplateConfig,
+) -> String {
+    let from_template = match location {
+        TemplateLocation::Archive(_) | TemplateLocation::RemoteArchive(_) => opened
+            .manifest
+            .as_ref()
+            .map(|m| m.name.to_owned())
+            .or_else(|| config.project.name.clone()),
+        _ => None,
+    };
+    from_template
+        .filter(|name| is_file_name_safe(name))
+        .unwrap_or_else(|| make_name_from_url(repo))
+}
+
+// Every lang of the template, or the ones picked with `--lang`.
+fn select_langs(template_fs: &dyn TemplateFs, wanted: &[String]) -> Result<Vec<String>> {
+    let available = template_langs(template_fs)?;
+    if available.is_empty() {
+        return Err(eyre!("💥 No language directories found in template."));
+    }
+    if wanted.is_empty() {
+        return Ok(available);
+    }
+
+    let missing = wanted
+        .iter()
+        .filter(|lang| !available.contains(lang))
+        .map(|lang| lang.as_str())
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        return Err(eyre!(
+            "💥 Template has no `{}` directory. Available: {}.",
+            missing.join("`, `"),
+            available.join(", ")
+        ));
+    }
+
+    let mut langs = wanted.to_vec();
+    langs.sort();
+    langs.dedup();
+    Ok(langs)
+}
+
+// Langs of the same template that are installed into `template_dir` already.
+async fn find_installed_langs(
+    app_state: &AppState,
+    name: &str,
+    repo: &str,
+    template_dir: &Path,
+) -> Result<Vec<TemplateResult>> {
+    if !template_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let find_params = TemplateFindParams {
+        name: Some(name.to_owned()),
+        repo: Some(repo.to_owned()),
+        ..Default::default()
+    };
+    let templates = app_state.local_db.find_templates(find_params).await?;
+    Ok(templates
+        .into_iter()
+        .filter(|t| Path::new(&t.template_dir) == template_dir)
+        .collect())
+}
+
 impl From<&Install> for CloneContext {
     #[tracing::instrument]
     fn from(cmd: &Install) -> Self {
+        let (repo, git_ref) = split_repo_ref(&cmd.template);
         Self {
-            url: cmd.template.to_owned(),
+            dest: None,
+            url: repo,
             branch: cmd.branch.to_owned(),
-            dest: Some(make_tmp_dir_from_url(&cmd.template)),
+            git_ref,
+            subdir: cmd.subdir.to_owned(),
         }
     }
 }
@@ -1,29 +1,101 @@
 use clap::Parser;
-use color_eyre::Result;
+use color_eyre::{Result, eyre::eyre};
 use tabled::{Table, settings::Style};
 use tracing::info;
 
-use crate::db::TabledTemplateListResult;
+use crate::db::{
+    ListTemplateOptions, TabledTemplateListResult, TemplateSort, Visibility, normalize_tags,
+};
 use crate::state::AppState;
+use crate::util::output::print_serialized;
+use crate::util::time::parse_timestamp;
 
 #[derive(Parser)]
 pub struct List {
-    #[arg(short = 'u', long)]
+    #[arg(short = 'u', long, help = "Only list public templates")]
     pub public: bool,
-    #[arg(short = 'p', long)]
+    #[arg(
+        short = 'p',
+        long,
+        conflicts_with = "public",
+        help = "Only list private templates"
+    )]
     pub private: bool,
+    #[arg(short, long, help = "Only list templates for this language")]
+    pub lang: Option<String>,
+    #[arg(short, long, help = "Only list templates installed from this repo")]
+    pub repo: Option<String>,
+    #[arg(short, long, help = "Only list templates that are part of this source")]
+    pub source: Option<String>,
+    #[arg(
+        short,
+        long = "tag",
+        value_name = "TAG",
+        help = "Only list templates with this tag (repeat to require several)"
+    )]
+    pub tags: Vec<String>,
+    #[arg(
+        long,
+        value_name = "DATE",
+        help = "Only list templates installed on or after DATE (YYYY-MM-DD or RFC 3339)"
+    )]
+    pub installed_after: Option<String>,
+    #[arg(long, value_enum, default_value_t = TemplateSort::Newest)]
+    pub sort: TemplateSort,
+    #[arg(short = 'n', long, help = "Maximum number of templates to list")]
+    pub limit: Option<u64>,
+    #[arg(long, requires = "limit", help = "Page to list, starting at 1")]
+    pub page: Option<u64>,
 }
 
-pub async fn list(app_state: &AppState, _cmd: &List) -> Result<()> {
+pub async fn list(app_state: &AppState, cmd: &List) -> Result<()> {
     let cache = app_state.local_db.clone();
 
-    let result = cache.list_templates(None).await?;
+    let offset = match (cmd.page, cmd.limit) {
+        (Some(0), _) => return Err(eyre!("💥 Pages start at 1.")),
+        (Some(page), Some(limit)) => Some((page - 1) * limit),
+        _ => None,
+    };
+    let filtered = cmd.lang.is_some()
+        || cmd.repo.is_some()
+        || cmd.source.is_some()
+        || !cmd.tags.is_empty()
+        || cmd.installed_after.is_some()
+        || cmd.public
+        || cmd.private
+        || cmd.page.is_some_and(|page| page > 1);
+    let opts = ListTemplateOptions {
+        ids: None,
+        sort: cmd.sort,
+        limit: cmd.limit,
+        offset,
+        lang: cmd.lang.clone(),
+        repo: cmd.repo.clone(),
+        source: cmd.source.clone(),
+        installed_after: cmd
+            .installed_after
+            .as_deref()
+            .map(parse_timestamp)
+            .transpose()?,
+        visibility: Visibility::from_flags(cmd.public, cmd.private),
+        tags: normalize_tags(&cmd.tags),
+    };
+
+    let result = cache.list_templates(Some(opts)).await?;
+
+    if !app_state.output_format.is_table() {
+        return print_serialized(app_state.output_format, &result);
+    }
 
     let rows = result
         .into_iter()
         .map(TabledTemplateListResult::from)
         .collect::<Vec<_>>();
 
+    if rows.is_empty() && filtered {
+        info!("No templates match.");
+        return Ok(());
+    }
     if rows.is_empty() {
         info!("No templates found in the cache.");
         info!("💡 Have a look at `boil install`");
@@ -1,16 +1,28 @@
+pub mod cache;
+pub mod history;
+pub mod info;
 pub mod install;
 pub mod list;
 pub mod new;
+pub mod outdated;
 pub mod remove;
 pub mod search;
 pub mod sources;
+pub mod tag;
+pub mod template;
 pub mod test;
 pub mod update;
 
+pub use cache::Cache;
+pub use history::{History, Regenerate, history, regenerate};
+pub use info::{Info, info};
 pub use install::{Install, install};
 pub use list::{List, list};
-pub use new::{New, new};
+pub use new::{GenerationReport, New, new};
+pub use outdated::{Outdated, outdated};
 pub use remove::{Remove, remove};
 pub use search::{Search, search};
 pub use sources::Sources;
+pub use tag::Tag;
+pub use template::Template;
 pub use update::{Update, update};
@@ -1,19 +1,41 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+    time::Instant,
+};
 
 use clap::Parser;
 use color_eyre::{eyre::eyre, Result};
+use serde::Serialize;
 use tabled::{settings::Style, Table, Tabled};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
-use crate::db::{TemplateFindParams, TemplateResult};
+use crate::commands::search::did_you_mean;
+use crate::db::{
+    is_secret_variable, redact_secrets, GenerationRow, SourceTemplateResult, TemplateFindParams,
+    TemplateResult, REDACTED,
+};
 use crate::state::AppState;
 use crate::template as tpl;
-use crate::util::file::{copy_dir, list_dir, move_file};
+use crate::template::archive::{open_template, TemplateLocation};
+use crate::template::fs::{
+    read_template_config, render_to_dir, sha256_hash_fs, DirFs, MemoryFs, TemplateFs,
+};
+use crate::util::file::move_file;
+use crate::util::output::print_serialized;
 
-#[derive(Debug, Parser)]
+#[derive(Debug, Clone, Parser)]
 pub struct New {
-    #[arg(required = true)]
+    #[arg(
+        required = true,
+        help = "Template name, <source>/<template>, @<preset> from the system config, or a git URL, local path or archive to generate from without installing"
+    )]
     pub name: String,
+    #[arg(
+        help = "Project name (default: the template name)",
+        conflicts_with = "rename"
+    )]
+    pub project: Option<String>,
     #[arg(short, long)]
     pub lang: Option<String>,
     #[arg(short, long)]
@@ -26,60 +48,448 @@ pub struct New {
     pub overwrite: bool,
     #[arg(short = 'v', long = "var", value_name = "KEY=VALUE")]
     pub vars: Vec<String>,
+    #[arg(short, long, help = "Branch to use when generating from a git URL")]
+    pub branch: Option<String>,
+    #[arg(
+        long,
+        help = "Subdirectory holding the template when generating from a URL or path"
+    )]
+    pub subdir: Option<String>,
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Ignore the values passed with `--var` for this template last time"
+    )]
+    pub fresh: bool,
+    #[arg(
+        long,
+        help = "Variable values from a preset of the template's boilermaker.toml"
+    )]
+    pub preset: Option<String>,
+}
+
+// The variables of a user preset from the system config, used as `@<alias>`.
+#[derive(Debug)]
+struct UserPresetVars {
+    alias: String,
+    vars: HashMap<String, String>,
+}
+
+// The template to generate from, read from wherever it lives: the install dir, a git mirror, an
+// archive or the source index.
+#[derive(Debug)]
+pub(crate) struct SelectedTemplate {
+    name: String,
+    lang: String,
+    pub(crate) fs: Box<dyn TemplateFs>,
+    origin: TemplateOrigin,
+}
+
+// Where the selected template came from, for the generation history.
+#[derive(Debug, Default)]
+struct TemplateOrigin {
+    template_id: Option<i64>,
+    repo: Option<String>,
+    branch: Option<String>,
+    subdir: Option<String>,
+    git_ref: Option<String>,
+    commit_sha: Option<String>,
+}
+
+// What `boil new` generated, printed with `--format json|yaml|csv`.
+#[derive(Debug, Serialize)]
+pub struct GenerationReport {
+    pub project: String,
+    pub template: String,
+    pub lang: String,
+    pub output_dir: String,
+    // Relative to `output_dir`.
+    pub files: Vec<String>,
+    // Secrets redacted, since this ends up in CI logs.
+    pub variables: BTreeMap<String, String>,
 }
 
 #[tracing::instrument]
 pub async fn new(app_state: &AppState, cmd: &New) -> Result<()> {
-    let project_name = cmd.rename.as_deref().unwrap_or(&cmd.name);
+    let started = Instant::now();
+    let (cmd, user_preset) = &resolve_user_preset(app_state, cmd)?;
+    // Nothing selected means several languages matched and the choices were printed.
+    let Some(t) = select_template(app_state, cmd).await? else {
+        return Ok(());
+    };
 
-    info!("Creating new project: {project_name}");
+    // URLs and paths are recorded as fetched: without `@<ref>`, and paths made absolute.
+    let template = match (is_template_location(&cmd.name), &t.origin.repo) {
+        (true, Some(repo)) => repo.to_owned(),
+        _ => cmd.name.to_owned(),
+    };
+    let report = generate_project(app_state, cmd, &template, &t, user_preset.as_ref()).await?;
+    record_generation(app_state, &template, &t, &report, started).await;
+    if !app_state.output_format.is_table() {
+        print_serialized(app_state.output_format, &report)?;
+    }
+
+    Ok(())
+}
+
+// `@<alias>` is replaced by the template of the user preset. Its lang and template preset apply
+// unless `--lang` or `--preset` are passed.
+fn resolve_user_preset(app_state: &AppState, cmd: &New) -> Result<(New, Option<UserPresetVars>)> {
+    let Some(alias) = cmd.name.strip_prefix('@') else {
+        return Ok((cmd.clone(), None));
+    };
+
+    let presets = app_state.sys_config.presets.clone().unwrap_or_default();
+    let Some(preset) = presets.get(alias) else {
+        let mut aliases = presets.keys().map(|a| format!("@{a}")).collect::<Vec<_>>();
+        aliases.sort();
+        return Err(eyre!(
+            "💥 No preset `@{}` in the system config. Presets: {}.",
+            alias,
+            if aliases.is_empty() {
+                "none".to_owned()
+            } else {
+                aliases.join(", ")
+            }
+        ));
+    };
+    info!("Using preset @{alias}: {}", preset.template);
+
+    let resolved = New {
+        name: preset.template.to_owned(),
+        lang: cmd.lang.clone().or_else(|| preset.lang.clone()),
+        preset: cmd.preset.clone().or_else(|| preset.preset.clone()),
+        ..cmd.clone()
+    };
+    let vars = UserPresetVars {
+        alias: alias.to_owned(),
+        vars: preset.vars(),
+    };
+    Ok((resolved, Some(vars)))
+}
+
+pub(crate) async fn select_template(
+    app_state: &AppState,
+    cmd: &New,
+) -> Result<Option<SelectedTemplate>> {
+    if is_template_location(&cmd.name) {
+        return Ok(Some(fetch_ephemeral_template(cmd).await?));
+    }
+
+    if cmd.branch.is_some() || cmd.subdir.is_some() {
+        return Err(eyre!(
+            "💥 `--branch` and `--subdir` only apply when generating from a URL or path."
+        ));
+    }
+
+    match cmd.name.split_once('/') {
+        Some((source_name, name)) => {
+            select_source_template(app_state, cmd, source_name, name).await
+        }
+        None => select_installed_template(app_state, cmd).await,
+    }
+}
 
+// Add the project to the generation history. The project is there already, so failing to record it
+// only warns.
+pub(crate) async fn record_generation(
+    app_state: &AppState,
+    template: &str,
+    t: &SelectedTemplate,
+    report: &GenerationReport,
+    started: Instant,
+) {
+    let template_hash = match sha256_hash_fs(t.fs.as_ref()) {
+        Ok(hash) => Some(hash),
+        Err(e) => {
+            warn!("Can't hash the template files: {e}");
+            None
+        }
+    };
+    let row = GenerationRow {
+        template_id: t.origin.template_id,
+        template: template.to_owned(),
+        name: t.name.to_owned(),
+        lang: t.lang.to_owned(),
+        repo: t.origin.repo.clone(),
+        branch: t.origin.branch.clone(),
+        subdir: t.origin.subdir.clone(),
+        git_ref: t.origin.git_ref.clone(),
+        commit_sha: t.origin.commit_sha.clone(),
+        template_hash,
+        project: report.project.to_owned(),
+        output_dir: report.output_dir.to_owned(),
+        // Secrets are already redacted in the report.
+        variables: report.variables.clone(),
+        duration_ms: started.elapsed().as_millis() as i64,
+    };
+
+    match app_state.local_db.create_generation(row).await {
+        Ok(id) => info!("Recorded as generation #{id} (see `boil history show {id}`)"),
+        Err(e) => warn!("Couldn't record the generation in the history: {e}"),
+    }
+}
+
+async fn select_installed_template(
+    app_state: &AppState,
+    cmd: &New,
+) -> Result<Option<SelectedTemplate>> {
     let existing_templates = get_existing_templates(app_state, cmd).await?;
     match existing_templates.len() {
         0 => {
-            return Err(eyre!("💥 Cannot find template: {}.", cmd.name));
+            let cache = app_state.local_db.clone();
+            let mut suggestions = cache.suggest_templates(&cmd.name).await?;
+            suggestions.extend(cache.suggest_source_templates(None, &cmd.name).await?);
+            return Err(eyre!(
+                "💥 Cannot find template: {}.{}",
+                cmd.name,
+                did_you_mean(&suggestions)
+            ));
         }
         2.. => {
-            print_multiple_template_results_help(&existing_templates);
-            return Ok(());
+            print_multiple_template_results_help(
+                existing_templates.iter().map(|t| (&t.name, &t.lang)),
+            );
+            return Ok(None);
         }
         _ => {}
     }
 
-    // Read template config. to get the default context & variables.
     let t = existing_templates.first().unwrap();
-    let base_dir = PathBuf::from(&t.template_dir);
-    let tpl_config = tpl::get_template_config(&base_dir)?;
+    Ok(Some(SelectedTemplate {
+        name: t.name.to_owned(),
+        lang: t.lang.to_owned(),
+        fs: Box::new(DirFs::new(&t.template_dir)),
+        origin: TemplateOrigin {
+            template_id: Some(t.id),
+            repo: Some(t.repo.to_owned()),
+            branch: t.branch.clone(),
+            subdir: t.subdir.clone(),
+            git_ref: t.git_ref.clone(),
+            commit_sha: t.commit_sha.clone(),
+        },
+    }))
+}
+
+// Generate from the content indexed by `boil sources add`, without installing the template.
+// Binary files aren't indexed, so templates with any are fetched from their repo instead.
+async fn select_source_template(
+    app_state: &AppState,
+    cmd: &New,
+    source_name: &str,
+    name: &str,
+) -> Result<Option<SelectedTemplate>> {
+    let cache = app_state.local_db.clone();
+    let templates = cache
+        .find_source_templates(source_name, name, cmd.lang.clone())
+        .await?;
+    let st = match templates.as_slice() {
+        [] => {
+            let suggestions = cache
+                .suggest_source_templates(Some(source_name.to_owned()), name)
+                .await?;
+            return Err(eyre!(
+                "💥 Cannot find template `{}` in source `{}`.{}",
+                name,
+                source_name,
+                did_you_mean(&suggestions)
+            ));
+        }
+        [st] => st,
+        _ => {
+            print_multiple_template_results_help(templates.iter().map(|t| (&t.name, &t.lang)));
+            return Ok(None);
+        }
+    };
+
+    let template_fs = open_source_template(app_state, st).await?;
+
+    Ok(Some(SelectedTemplate {
+        name: st.name.to_owned(),
+        lang: st.lang.to_owned(),
+        fs: template_fs,
+        origin: TemplateOrigin {
+            repo: Some(st.repo.to_owned()),
+            branch: st.branch.clone(),
+            subdir: st.subdir.clone(),
+            ..Default::default()
+        },
+    }))
+}
+
+// The files of a source template: from the source index when everything was indexed, otherwise
+// straight from its repo.
+pub(crate) async fn open_source_template(
+    app_state: &AppState,
+    st: &SourceTemplateResult,
+) -> Result<Box<dyn TemplateFs>> {
+    let files = app_state
+        .local_db
+        .get_source_template_content(st.id)
+        .await?
+        .into_iter()
+        .map(|f| (f.file_path, f.content))
+        .collect();
+    if let Some(indexed) = MemoryFs::from_indexed_files(files)? {
+        debug!(
+            "Reading {}/{} from the source index",
+            st.source_name, st.name
+        );
+        return Ok(Box::new(indexed));
+    }
+
+    info!("Fetching {}/{} from {}", st.source_name, st.name, st.repo);
+    let location = TemplateLocation::detect(&st.repo);
+    let ctx =
+        tpl::CloneContext::new(&st.repo, None, st.branch.clone()).with_subdir(st.subdir.clone());
+    Ok(open_template(&location, &ctx).await?.fs)
+}
+
+async fn generate_project(
+    app_state: &AppState,
+    cmd: &New,
+    template: &str,
+    t: &SelectedTemplate,
+    user_preset: Option<&UserPresetVars>,
+) -> Result<GenerationReport> {
+    // Read template config. to get the default context & variables.
+    let tpl_config = read_template_config(t.fs.as_ref())?;
     let mut context = tpl_config
         .variables
         .as_ref()
         .map(|vars| vars.as_map().clone())
         .unwrap_or_default();
+    let mut origins: BTreeMap<String, String> = context
+        .keys()
+        .map(|name| (name.to_owned(), "template".to_owned()))
+        .collect();
+    let used_vars = tpl::static_analysis::find_variables_in_fs(t.fs.as_ref(), None)?;
 
-    // Validate extra variables from CLI or app.
-    if !cmd.vars.is_empty() {
-        let user_vars = vec_to_hashmap(&cmd.vars)?;
-        extend_template_context(&mut context, &t.template_dir, user_vars)?;
+    // The values passed for this template last time replace its defaults. Ones for variables the
+    // template doesn't use anymore are left out.
+    if !cmd.fresh {
+        for (name, value) in app_state.local_db.get_answers(template).await? {
+            if used_vars.contains(&name) {
+                origins.insert(name.to_owned(), "last time".to_owned());
+                context.insert(name, value);
+            }
+        }
+    }
+
+    // Presets are picked for this run, so they win over the values from last time.
+    if let Some(name) = &cmd.preset {
+        let Some(vars) = tpl_config.preset(name) else {
+            let names = tpl_config.preset_names();
+            return Err(eyre!(
+                "💥 Template has no preset `{}`. Presets: {}.",
+                name,
+                if names.is_empty() {
+                    "none".to_owned()
+                } else {
+                    names.join(", ")
+                }
+            ));
+        };
+        let from = format!("preset {name}");
+        extend_template_context(&mut context, &mut origins, &used_vars, vars, &from)?;
+    }
+    if let Some(preset) = user_preset {
+        let from = format!("@{}", preset.alias);
+        let vars = preset.vars.clone();
+        extend_template_context(&mut context, &mut origins, &used_vars, vars, &from)?;
     }
+
+    // Validate extra variables from CLI or app.
+    let user_vars = vec_to_hashmap(&cmd.vars)?;
+    let vars = user_vars.clone();
+    extend_template_context(&mut context, &mut origins, &used_vars, vars, "--var")?;
     debug!("Template context: {:?}", context);
 
-    // Copy template to work-dir before rendering.
-    let work_dir = tpl::create_work_dir_clean(&t.name)?;
-    let template_base_dir = PathBuf::from(&t.template_dir);
-    let template_dir = template_base_dir.join(&t.lang);
-    copy_dir(&template_dir, &work_dir).await?;
+    if app_state.output_format.is_table() {
+        print_variables(&context, &origins);
+    }
 
-    let template_paths: Vec<PathBuf> = list_dir(&work_dir)
-        .await?
+    let project_name = cmd
+        .rename
+        .as_deref()
+        .or(cmd.project.as_deref())
+        .unwrap_or(&t.name);
+    let report = write_project(t, project_name, cmd.dir.as_deref(), cmd.overwrite, context).await?;
+
+    let answers = user_vars.into_iter().collect();
+    if let Err(e) = app_state.local_db.save_answers(template, &answers).await {
+        warn!("Couldn't remember the variables for next time: {e}");
+    }
+
+    Ok(report)
+}
+
+#[derive(Debug, Tabled)]
+struct VariableRow {
+    #[tabled(rename = "Variable")]
+    name: String,
+    #[tabled(rename = "Value")]
+    value: String,
+    #[tabled(rename = "From")]
+    from: String,
+}
+
+// The variables the project is made with, and whether they're the template defaults, the values
+// from last time, from a preset or `--var`.
+fn print_variables(context: &HashMap<String, String>, origins: &BTreeMap<String, String>) {
+    if context.is_empty() {
+        return;
+    }
+
+    let rows = origins
         .iter()
-        .filter(|p| p.is_file())
-        .map(|p| p.to_path_buf())
-        .collect();
-    if let Err(e) = tpl::render_template_files(template_paths, context).await {
+        .filter_map(|(name, from)| {
+            let value = context.get(name)?;
+            Some(VariableRow {
+                name: name.to_owned(),
+                value: if is_secret_variable(name) {
+                    REDACTED.to_owned()
+                } else {
+                    value.to_owned()
+                },
+                from: from.to_owned(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut table = Table::new(&rows);
+    table.with(Style::psql());
+    print!("\n🔣 Variables\n\n{table}\n\n");
+
+    if origins.values().any(|from| *from == "last time") {
+        info!(
+            "💡 Pass `--fresh` to use the template defaults instead of the values from last time."
+        );
+    }
+}
+
+// Render the template with `context` into `<dir>/<project_name>`.
+pub(crate) async fn write_project(
+    t: &SelectedTemplate,
+    project_name: &str,
+    dir: Option<&str>,
+    overwrite: bool,
+    context: HashMap<String, String>,
+) -> Result<GenerationReport> {
+    info!("Creating new project: {project_name}");
+
+    // Render into a work-dir first, so a failed render leaves no half-written project behind.
+    let work_dir = tpl::create_work_dir_clean(&t.name)?;
+    if let Err(e) = render_to_dir(t.fs.as_ref(), &t.lang, context.clone(), &work_dir) {
+        tpl::clean_dir(&work_dir)?;
         return Err(eyre!("💥 Failed to render template files: {e}"));
     }
+    let files = DirFs::new(&work_dir)
+        .list_files()?
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
 
-    let out_dir = tpl::create_project_dir(project_name, cmd.dir.as_deref(), cmd.overwrite).await?;
+    let out_dir = tpl::create_project_dir(project_name, dir, overwrite).await?;
 
     if let Err(e) = move_file(&work_dir, &out_dir).await {
         return Err(eyre!("💥 Failed to move project to output directory: {e}"));
@@ -88,7 +498,62 @@ pub async fn new(app_state: &AppState, cmd: &New) -> Result<()> {
     info!("Project created at: {}", out_dir.display());
     info!("All set. Happy hacking! 🚀");
 
-    Ok(())
+    Ok(GenerationReport {
+        project: project_name.to_owned(),
+        template: t.name.to_owned(),
+        lang: t.lang.to_owned(),
+        output_dir: out_dir.display().to_string(),
+        files,
+        variables: redact_secrets(context.into_iter().collect()),
+    })
+}
+
+// Anything that isn't a plain template name: URLs, `git@host:org/repo`, or an existing path.
+// Bare names are never paths (use `./name`), so an installed template always wins.
+pub(crate) fn is_template_location(name: &str) -> bool {
+    let (repo, _) = tpl::split_repo_ref(name);
+    let is_url = repo.contains("://") || (repo.contains('@') && repo.contains(':'));
+    let is_path = (repo.contains('/') || repo.starts_with('.')) && Path::new(&repo).exists();
+    is_url || is_path
+}
+
+// Read the template straight from its location, without installing it or registering it in the
+// local cache.
+async fn fetch_ephemeral_template(cmd: &New) -> Result<SelectedTemplate> {
+    let (repo, git_ref) = tpl::split_repo_ref(&cmd.name);
+    if git_ref.is_some() && cmd.branch.is_some() {
+        return Err(eyre!(
+            "💥 Use either `<repo>@<ref>` or `--branch`, not both."
+        ));
+    }
+
+    let location = TemplateLocation::detect(&repo);
+    let ctx = tpl::CloneContext::new(&repo, None, cmd.branch.clone())
+        .with_ref(git_ref)
+        .with_subdir(cmd.subdir.clone());
+    let opened = match open_template(&location, &ctx).await {
+        Ok(opened) => opened,
+        Err(err) => return Err(eyre!("💥 Failed to fetch template: {}", err)),
+    };
+
+    let tpl_config = read_template_config(opened.fs.as_ref())?;
+    let lang = tpl::get_lang(&tpl_config, &cmd.lang)?;
+    let commit_sha = opened.commit_sha();
+    let git_ref = opened.cloned.as_ref().map(|c| c.git_ref.to_owned());
+
+    Ok(SelectedTemplate {
+        name: tpl::make_name_from_url(&repo),
+        lang,
+        fs: opened.fs,
+        origin: TemplateOrigin {
+            repo: Some(location.to_repo_string().unwrap_or(repo)),
+            branch: cmd.branch.clone(),
+            subdir: cmd.subdir.clone(),
+            git_ref,
+            commit_sha,
+            ..Default::default()
+        },
+    })
 }
 
 async fn get_existing_templates(app_state: &AppState, cmd: &New) -> Result<Vec<TemplateResult>> {
@@ -100,6 +565,7 @@ async fn get_existing_templates(app_state: &AppState, cmd: &New) -> Result<Vec<T
         branch: None,
         subdir: None,
         sha256_hash: None,
+        source: None,
     };
 
     let cache = app_state.local_db.clone();
@@ -117,13 +583,15 @@ struct MultipleResultsRow {
     lang: String,
 }
 
-fn print_multiple_template_results_help(template_rows: &Vec<TemplateResult>) {
+fn print_multiple_template_results_help<'a>(
+    template_rows: impl IntoIterator<Item = (&'a String, &'a String)>,
+) {
     let help_line = "Multiple templates found. (You need to provide --lang)";
     let mut help_rows = Vec::new();
-    for t in template_rows {
+    for (name, lang) in template_rows {
         help_rows.push(MultipleResultsRow {
-            template: t.name.clone(),
-            lang: t.lang.clone(),
+            template: name.clone(),
+            lang: lang.clone(),
         });
     }
 
@@ -133,7 +601,7 @@ fn print_multiple_template_results_help(template_rows: &Vec<TemplateResult>) {
 }
 
 // Turn a vec like ["foo=bar", "baz=quux"] into a HashMap
-fn vec_to_hashmap(vec: &[String]) -> Result<HashMap<String, String>> {
+pub(crate) fn vec_to_hashmap(vec: &[String]) -> Result<HashMap<String, String>> {
     vec.iter()
         .map(|mapping| {
             mapping
@@ -144,12 +612,14 @@ fn vec_to_hashmap(vec: &[String]) -> Result<HashMap<String, String>> {
         .collect()
 }
 
+// `from` is where the variables come from, shown along with their values.
 fn extend_template_context(
     template_context: &mut HashMap<String, String>,
-    template_dir: &str,
+    origins: &mut BTreeMap<String, String>,
+    allowed_vars: &HashSet<String>,
     user_vars: HashMap<String, String>,
+    from: &str,
 ) -> Result<()> {
-    let allowed_vars = tpl::static_analysis::find_variables_in_path(template_dir)?;
     let bad_vars: Vec<_> = user_vars
         .keys()
         .filter(|var| !allowed_vars.contains(*var))
@@ -158,11 +628,15 @@ fn extend_template_context(
 
     if !bad_vars.is_empty() {
         return Err(eyre!(
-            "💥 Some variables aren't available in template: {}.\nKnown variables: {:?}",
+            "💥 Some variables ({}) aren't available in template: {}.\nKnown variables: {:?}",
+            from,
             bad_vars.join(", "),
             allowed_vars,
         ));
     }
+    for name in user_vars.keys() {
+        origins.insert(name.to_owned(), from.to_owned());
+    }
     template_context.extend(user_vars);
 
     Ok(())
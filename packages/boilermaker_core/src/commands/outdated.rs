@@ -0,0 +1,204 @@
+use clap::Parser;
+use color_eyre::{Result, eyre::eyre};
+use serde::Serialize;
+use tabled::{Table, Tabled, settings::Style};
+use tokio::task::JoinSet;
+use tracing::info;
+
+use crate::db::{TemplateFindParams, TemplateResult};
+use crate::state::AppState;
+use crate::template::archive::TemplateLocation;
+use crate::template::{count_commits_between, find_remote_commit, short_sha};
+use crate::util::output::print_serialized;
+
+#[derive(Debug, Parser)]
+pub struct Outdated {
+    #[arg(help = "Only check templates with this name")]
+    pub template: Option<String>,
+    #[arg(short, long, help = "Only check templates with this language")]
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutdatedStatus {
+    UpToDate,
+    // Local dirs and archives have no remote commit to compare against.
+    Untracked,
+    Outdated {
+        latest: String,
+        behind: Option<usize>,
+    },
+    Unknown(String),
+}
+
+// A checked template, printed with `--format json|yaml|csv`.
+#[derive(Debug, Serialize)]
+pub struct OutdatedResult {
+    pub id: i64,
+    pub name: String,
+    pub lang: String,
+    pub git_ref: Option<String>,
+    pub current: Option<String>,
+    pub latest: Option<String>,
+    pub behind: Option<usize>,
+    // `up_to_date`, `outdated`, `untracked` or `unknown`.
+    pub status: String,
+    pub error: Option<String>,
+}
+
+impl OutdatedResult {
+    pub fn from(t: &TemplateResult, status: &OutdatedStatus) -> Self {
+        let (latest, behind, status, error) = match status {
+            OutdatedStatus::UpToDate => (t.commit_sha.clone(), Some(0), "up_to_date", None),
+            OutdatedStatus::Outdated { latest, behind } => {
+                (Some(latest.to_owned()), *behind, "outdated", None)
+            }
+            OutdatedStatus::Untracked => (None, None, "untracked", None),
+            OutdatedStatus::Unknown(err) => (None, None, "unknown", Some(err.to_owned())),
+        };
+        Self {
+            id: t.id,
+            name: t.name.to_owned(),
+            lang: t.lang.to_owned(),
+            git_ref: t.git_ref.clone(),
+            current: t.commit_sha.clone(),
+            latest,
+            behind,
+            status: status.to_owned(),
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Tabled)]
+pub struct TabledOutdatedResult {
+    pub id: i64,
+    pub name: String,
+    pub lang: String,
+    #[tabled(rename = "ref")]
+    pub git_ref: String,
+    pub current: String,
+    pub latest: String,
+    pub behind: String,
+    pub status: String,
+}
+
+impl TabledOutdatedResult {
+    pub fn from(t: &TemplateResult, status: &OutdatedStatus) -> Self {
+        let current = t
+            .commit_sha
+            .as_deref()
+            .map(short_sha)
+            .unwrap_or_else(|| "-".to_owned());
+        let (latest, behind, status) = match status {
+            OutdatedStatus::UpToDate => (current.clone(), "0".to_owned(), "up to date".to_owned()),
+            OutdatedStatus::Outdated { latest, behind } => (
+                short_sha(latest),
+                behind
+                    .map(|n| n.to_string())
+                    .unwrap_or_else(|| "?".to_owned()),
+                "outdated".to_owned(),
+            ),
+            OutdatedStatus::Untracked => {
+                ("-".to_owned(), "-".to_owned(), "not a git repo".to_owned())
+            }
+            OutdatedStatus::Unknown(err) => ("-".to_owned(), "-".to_owned(), err.to_owned()),
+        };
+        Self {
+            id: t.id,
+            name: t.name.to_owned(),
+            lang: t.lang.to_owned(),
+            git_ref: t.git_ref.clone().unwrap_or_else(|| "-".to_owned()),
+            current,
+            latest,
+            behind,
+            status,
+        }
+    }
+}
+
+#[tracing::instrument]
+pub async fn outdated(app_state: &AppState, cmd: &Outdated) -> Result<()> {
+    let cache = app_state.local_db.clone();
+    let find_params = TemplateFindParams {
+        name: cmd.template.clone(),
+        lang: cmd.lang.clone(),
+        ..Default::default()
+    };
+    let templates = cache.find_templates(find_params).await?;
+    if templates.is_empty() {
+        info!("No templates found in the cache.");
+        return Ok(());
+    }
+
+    // The checks fetch with libgit2, which blocks, so they run on the blocking pool.
+    let mut tasks = JoinSet::new();
+    for templ in templates {
+        tasks.spawn_blocking(move || {
+            let status = check_template(&templ);
+            (templ, status)
+        });
+    }
+
+    let mut results = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        results.push(joined?);
+    }
+    results.sort_by_key(|(t, _)| t.id);
+
+    if app_state.output_format.is_table() {
+        let rows = results
+            .iter()
+            .map(|(t, s)| TabledOutdatedResult::from(t, s))
+            .collect::<Vec<_>>();
+        let mut table = Table::new(&rows);
+        table.with(Style::psql());
+        print!("\n\n{table}\n\n");
+    } else {
+        let rows = results
+            .iter()
+            .map(|(t, s)| OutdatedResult::from(t, s))
+            .collect::<Vec<_>>();
+        print_serialized(app_state.output_format, &rows)?;
+    }
+
+    let outdated = results
+        .iter()
+        .filter(|(_, s)| matches!(s, OutdatedStatus::Outdated { .. }))
+        .count();
+    let unknown = results
+        .iter()
+        .filter(|(_, s)| matches!(s, OutdatedStatus::Unknown(_)))
+        .count();
+
+    if outdated > 0 || unknown > 0 {
+        return Err(eyre!(
+            "💥 {} template(s) outdated, {} could not be checked.",
+            outdated,
+            unknown
+        ));
+    }
+
+    info!("✅ All templates are up to date.");
+    Ok(())
+}
+
+#[tracing::instrument]
+pub fn check_template(templ: &TemplateResult) -> OutdatedStatus {
+    if !TemplateLocation::detect(&templ.repo).is_git() {
+        return OutdatedStatus::Untracked;
+    }
+
+    let Some(current) = &templ.commit_sha else {
+        return OutdatedStatus::Unknown("no commit recorded (run `boil update`)".to_owned());
+    };
+
+    match find_remote_commit(&templ.repo, templ.git_ref.as_deref()) {
+        Ok(Some(latest)) if &latest != current => OutdatedStatus::Outdated {
+            behind: count_commits_between(&templ.repo, current, &latest),
+            latest,
+        },
+        Ok(_) => OutdatedStatus::UpToDate,
+        Err(err) => OutdatedStatus::Unknown(err.to_string()),
+    }
+}
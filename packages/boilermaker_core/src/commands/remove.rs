@@ -5,9 +5,10 @@ use color_eyre::{Result, eyre::eyre};
 use tabled::{Table, settings::Style};
 use tracing::info;
 
-use crate::db::TabledTemplateListResult;
+use crate::db::{TabledTemplateListResult, TemplateFindParams};
 use crate::state::AppState;
 use crate::template::remove_dir_if_exists;
+use crate::util::output::print_serialized;
 
 #[derive(Parser)]
 pub struct Remove {
@@ -29,17 +30,36 @@ pub async fn remove(app_state: &AppState, cmd: &Remove) -> Result<()> {
 
     let template_dir = PathBuf::from(&template.template_dir);
 
-    let rows = vec![template]
-        .into_iter()
-        .map(TabledTemplateListResult::from)
-        .collect::<Vec<_>>();
+    if app_state.output_format.is_table() {
+        let rows = vec![template.clone()]
+            .into_iter()
+            .map(TabledTemplateListResult::from)
+            .collect::<Vec<_>>();
 
-    let mut table = Table::new(&rows);
-    table.with(Style::psql());
+        let mut table = Table::new(&rows);
+        table.with(Style::psql());
 
-    print!("\n{table}\n\n");
+        print!("\n{table}\n\n");
+    }
+
+    // Other langs of the template are installed into the same dir.
+    let find_params = TemplateFindParams {
+        name: Some(template.name.to_owned()),
+        ..Default::default()
+    };
+    let shared = cache
+        .find_templates(find_params)
+        .await?
+        .iter()
+        .any(|t| t.id != template.id && t.template_dir == template.template_dir);
 
-    if let Err(err) = remove_dir_if_exists(&template_dir) {
+    if shared {
+        info!(
+            "Keeping {}, other languages of {} use it.",
+            template_dir.display(),
+            template.name
+        );
+    } else if let Err(err) = remove_dir_if_exists(&template_dir) {
         return Err(eyre!("💥 Failed to remove template directory: {}", err));
     }
 
@@ -47,5 +67,9 @@ pub async fn remove(app_state: &AppState, cmd: &Remove) -> Result<()> {
 
     info!("Removed template: {}", removed_id);
 
+    if !app_state.output_format.is_table() {
+        print_serialized(app_state.output_format, &template)?;
+    }
+
     Ok(())
 }
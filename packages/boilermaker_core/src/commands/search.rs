@@ -2,21 +2,49 @@ use std::sync::Arc;
 
 use clap::Parser;
 use color_eyre::{Result, eyre::eyre};
+use nu_ansi_term::{Color, Style as TermStyle};
 use tabled::Table;
 use tabled::settings::Style;
 use tracing::info;
 
-use crate::db::{SearchResult, TabledSearchResult, TemplateDb};
+use crate::db::{
+    SNIPPET_END, SNIPPET_START, SearchQuery, SearchResult, TabledSearchResult, TemplateDb,
+    Visibility,
+};
 use crate::state::AppState;
+use crate::util::output::print_serialized;
 
 #[derive(Debug, Parser)]
 pub struct Search {
-    #[arg(required = true, help = "Search term")]
+    #[arg(
+        required = true,
+        help = "Search terms",
+        long_help = "Search terms. Use \"quotes\" for phrases, `term*` for prefixes, AND/OR/NOT \
+                     between terms and lang:, name:, path:, source: or tag: to filter, e.g. \
+                     `axum OR actix lang:rust path:Dockerfile`. NOT excludes per file, so a \
+                     template can still match through a file without the excluded term"
+    )]
     pub term: String,
     #[arg(short = 'l', long, help = "Search only installed templates")]
     pub local: bool,
     #[arg(short = 's', long, help = "Search a specific source")]
     pub src: Option<String>,
+    #[arg(
+        short = 'n',
+        long,
+        default_value_t = 20,
+        help = "Maximum number of templates to show"
+    )]
+    pub limit: usize,
+    #[arg(short = 'u', long, help = "Only show public templates")]
+    pub public: bool,
+    #[arg(
+        short = 'p',
+        long,
+        conflicts_with = "public",
+        help = "Only show private templates"
+    )]
+    pub private: bool,
 }
 
 pub async fn search(app_state: &AppState, cmd: &Search) -> Result<()> {
@@ -29,24 +57,78 @@ pub async fn search(app_state: &AppState, cmd: &Search) -> Result<()> {
         ));
     }
 
+    let mut query = SearchQuery::parse(&term)?;
+    query.visibility = Visibility::from_flags(cmd.public, cmd.private);
     let scope = SearchScope::from(cmd);
-    let search_results = search_templates(cache, &term, scope).await?;
+    let mut search_results = search_templates(cache.clone(), &query, scope.clone()).await?;
+    search_results.truncate(cmd.limit);
+    if !app_state.output_format.is_table() {
+        print_serialized(app_state.output_format, &search_results)?;
+    }
     if search_results.is_empty() {
         info!("No results found for {term}.");
+        let suggestions = suggest_templates(cache, &query, &scope).await?;
+        if !suggestions.is_empty() {
+            info!("💡{}", did_you_mean(&suggestions));
+        }
+        return Ok(());
+    }
+    if !app_state.output_format.is_table() {
         return Ok(());
     }
 
     let tabled: Vec<TabledSearchResult> = search_results
-        .into_iter()
+        .iter()
+        .cloned()
         .map(TabledSearchResult::from)
         .collect();
     let mut table = Table::new(tabled);
     table.with(Style::psql());
     print!("\n\n{table}\n\n");
 
+    print_matches(&search_results);
+
     Ok(())
 }
 
+// Matches shown per template.
+const MAX_MATCHES: usize = 3;
+
+fn print_matches(results: &[SearchResult]) {
+    for result in results {
+        println!(
+            "{} ({}, {} #{})",
+            TermStyle::new().bold().paint(&result.name),
+            result.lang,
+            result.kind,
+            result.id
+        );
+        for m in result.matches.iter().take(MAX_MATCHES) {
+            let location = m.file_path.as_deref().unwrap_or("template metadata");
+            println!("  {}", Color::Cyan.paint(location));
+            if let Some(snippet) = &m.snippet {
+                println!("    {}", highlight_snippet(snippet));
+            }
+        }
+        if result.matches.len() > MAX_MATCHES {
+            println!("  … and {} more", result.matches.len() - MAX_MATCHES);
+        }
+        println!();
+    }
+}
+
+// Snippets come on one line, with the matched terms in bold yellow.
+fn highlight_snippet(snippet: &str) -> String {
+    let highlight = Color::Yellow.bold();
+    snippet
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(SNIPPET_START, &highlight.prefix().to_string())
+        .replace(SNIPPET_END, &highlight.suffix().to_string())
+}
+
+#[derive(Debug, Clone)]
 pub enum SearchScope {
     Local,
     Source(String),
@@ -67,20 +149,69 @@ impl SearchScope {
 
 pub async fn search_templates(
     cache: Arc<dyn TemplateDb>,
-    term: &str,
+    query: &SearchQuery,
     scope: SearchScope,
 ) -> Result<Vec<SearchResult>> {
     match scope {
-        SearchScope::Local => Ok(cache.search_templates(term).await?),
-        SearchScope::Source(name) => Ok(cache.search_sources(Some(name), term).await?),
+        SearchScope::Local => Ok(cache.search_templates(query).await?),
+        SearchScope::Source(name) => {
+            let mut query = query.clone();
+            query.sources.push(name);
+            Ok(cache.search_sources(&query).await?)
+        }
         SearchScope::All => {
             let mut all_results = Vec::new();
-            let local = cache.search_templates(term).await?;
+            let local = cache.search_templates(query).await?;
             all_results.extend(local);
 
-            let sources = cache.search_sources(None, term).await?;
+            let sources = cache.search_sources(query).await?;
             all_results.extend(sources);
+            all_results.sort_by(|a, b| b.score.total_cmp(&a.score));
             Ok(all_results)
         }
     }
 }
+
+// Template names close to the searched words (and `name:` filters), for when nothing matched.
+pub async fn suggest_templates(
+    cache: Arc<dyn TemplateDb>,
+    query: &SearchQuery,
+    scope: &SearchScope,
+) -> Result<Vec<String>> {
+    let mut suggestions: Vec<String> = Vec::new();
+    for word in query.words.iter().chain(&query.names) {
+        let found = match scope {
+            SearchScope::Local => cache.suggest_templates(word).await?,
+            SearchScope::Source(name) => {
+                cache
+                    .suggest_source_templates(Some(name.to_owned()), word)
+                    .await?
+            }
+            SearchScope::All => {
+                let mut found = cache.suggest_templates(word).await?;
+                found.extend(cache.suggest_source_templates(None, word).await?);
+                found
+            }
+        };
+        for suggestion in found {
+            // The searched name itself can come back when filters hid it.
+            if suggestion != *word && !suggestions.contains(&suggestion) {
+                suggestions.push(suggestion);
+            }
+        }
+    }
+    Ok(suggestions)
+}
+
+// " Did you mean `a` or `b`?", or nothing without suggestions.
+pub fn did_you_mean(suggestions: &[String]) -> String {
+    let quoted = suggestions
+        .iter()
+        .map(|s| format!("`{s}`"))
+        .collect::<Vec<_>>();
+    match quoted.as_slice() {
+        [] => String::new(),
+        [one] => format!(" Did you mean {one}?"),
+        [rest @ .., last] => format!(" Did you mean {} or {last}?", rest.join(", ")),
+    }
+}
@@ -1,5 +1,4 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 use color_eyre::{Result, eyre::eyre};
@@ -9,11 +8,12 @@ use tracing::info;
 
 use crate::db::TabledSourceRow;
 use crate::db::source::{PartialSourceTemplateRow, SourceRow};
+use crate::db::{TemplateMetadata, Visibility};
 use crate::state::AppState;
-use crate::template::{
-    CloneContext, clean_dir, clone_repo, get_lang, get_template_config, make_name_from_url,
-    make_tmp_dir_from_url,
-};
+use crate::template::archive::{TemplateLocation, open_template};
+use crate::template::fs::{TemplateFs, read_template_config};
+use crate::template::{CloneContext, get_lang, make_name_from_url};
+use crate::util::output::print_serialized;
 use crate::util::string;
 
 #[derive(Subcommand)]
@@ -62,7 +62,8 @@ pub async fn add(app_state: &AppState, cmd: &Add) -> Result<()> {
     };
     let source_row = source_row.set_hash_string();
 
-    let mut partial_source_template_rows: Vec<(PathBuf, PartialSourceTemplateRow)> = Vec::new();
+    let mut partial_source_template_rows: Vec<(Box<dyn TemplateFs>, PartialSourceTemplateRow)> =
+        Vec::new();
     for template in src_cnf.templates.iter() {
         let repo = match template.get("repo") {
             Some(repo) => repo,
@@ -76,24 +77,14 @@ pub async fn add(app_state: &AppState, cmd: &Add) -> Result<()> {
         };
 
         let repo_ctx = CloneContext::from(template);
-        let clone_dir = repo_ctx.dest.as_ref().unwrap();
-
-        if let Err(err) = clean_dir(clone_dir) {
-            return Err(eyre!("💥 Failed setting up clone dir: {}", err));
-        }
 
         info!("Cloning source template: {name}");
-        if let Err(err) = clone_repo(&repo_ctx).await {
-            return Err(eyre!("💥 Failed to clone template: {}", err));
-        }
-
-        let work_dir = if let Some(subdir) = &template.get("subdir") {
-            clone_dir.join(subdir)
-        } else {
-            clone_dir.to_path_buf()
+        let opened = match open_template(&TemplateLocation::detect(repo), &repo_ctx).await {
+            Ok(opened) => opened,
+            Err(err) => return Err(eyre!("💥 Failed to clone template: {}", err)),
         };
 
-        let cnf = get_template_config(work_dir.as_path())?;
+        let cnf = read_template_config(opened.fs.as_ref())?;
         let lang = get_lang(&cnf, &template.get("lang").cloned())?;
 
         let partial_row = PartialSourceTemplateRow {
@@ -102,9 +93,12 @@ pub async fn add(app_state: &AppState, cmd: &Add) -> Result<()> {
             repo: repo.to_owned(),
             branch: template.get("branch").cloned(),
             subdir: template.get("subdir").cloned(),
+            metadata: TemplateMetadata::from(&cnf.project),
+            visibility: Visibility::infer(repo),
+            tags: cnf.project.tags.clone().unwrap_or_default(),
         };
 
-        partial_source_template_rows.push((work_dir, partial_row));
+        partial_source_template_rows.push((opened.fs, partial_row));
     }
 
     let sources = app_state.local_db.clone();
@@ -150,6 +144,9 @@ impl From<&HashMap<String, String>> for SourceMap {
 #[tracing::instrument]
 pub async fn list(app_state: &AppState, _cmd: &List) -> Result<()> {
     let sources = app_state.local_db.list_sources().await?;
+    if !app_state.output_format.is_table() {
+        return print_serialized(app_state.output_format, &sources);
+    }
     if sources.is_empty() {
         info!("No sources found.");
         info!("💡 Have a look at `boil sources add`");
@@ -172,9 +169,11 @@ impl From<&HashMap<String, String>> for CloneContext {
     fn from(m: &HashMap<String, String>) -> Self {
         let repo = m.get("repo").cloned().unwrap();
         Self {
-            url: repo.clone(),
+            url: repo,
             branch: m.get("branch").cloned(),
-            dest: Some(make_tmp_dir_from_url(&repo)),
+            dest: None,
+            git_ref: None,
+            subdir: m.get("subdir").cloned(),
         }
     }
 }
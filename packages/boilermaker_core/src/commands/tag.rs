@@ -0,0 +1,204 @@
+use clap::{Parser, Subcommand};
+use color_eyre::{Result, eyre::eyre};
+use serde::Serialize;
+use tabled::{Table, Tabled, settings::Style};
+use tracing::info;
+
+use crate::db::{SearchResultKind, TagOrigin, TemplateFindParams, normalize_tags};
+use crate::state::AppState;
+use crate::util::output::print_serialized;
+
+#[derive(Subcommand)]
+pub enum Tag {
+    #[command(about = "Tag a template")]
+    Add(Add),
+    #[command(about = "Remove tags from a template")]
+    Remove(Remove),
+    #[command(about = "List all tags, or the tags of a template")]
+    List(List),
+}
+
+#[derive(Debug, Parser)]
+pub struct Add {
+    #[arg(required = true, help = "Template ID, name, or <source>/<template>")]
+    pub template: String,
+    #[arg(required = true, help = "Tags to add")]
+    pub tags: Vec<String>,
+    #[arg(short, long, help = "Only tag the given language of the template")]
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct Remove {
+    #[arg(required = true, help = "Template ID, name, or <source>/<template>")]
+    pub template: String,
+    #[arg(required = true, help = "Tags to remove")]
+    pub tags: Vec<String>,
+    #[arg(short, long, help = "Only untag the given language of the template")]
+    pub lang: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct List {
+    #[arg(help = "Template ID, name, or <source>/<template> (default: all tags)")]
+    pub template: Option<String>,
+    #[arg(short, long, help = "Only show the given language of the template")]
+    pub lang: Option<String>,
+}
+
+// An installed or source template, with the tags it has.
+#[derive(Debug, Serialize, Tabled)]
+struct TaggedTemplate {
+    kind: SearchResultKind,
+    id: i64,
+    name: String,
+    lang: String,
+    #[tabled(display = "display_tags")]
+    tags: Option<String>,
+}
+
+fn display_tags(tags: &Option<String>) -> String {
+    tags.clone().unwrap_or_else(|| "-".to_owned())
+}
+
+#[tracing::instrument]
+pub async fn add(app_state: &AppState, cmd: &Add) -> Result<()> {
+    let tags = valid_tags(&cmd.tags)?;
+    let cache = app_state.local_db.clone();
+
+    for t in find_templates(app_state, &cmd.template, &cmd.lang).await? {
+        match t.kind {
+            SearchResultKind::Template => {
+                cache
+                    .add_template_tags(t.id, &tags, TagOrigin::User)
+                    .await?
+            }
+            SearchResultKind::Source => {
+                cache
+                    .add_source_template_tags(t.id, &tags, TagOrigin::User)
+                    .await?
+            }
+        }
+        info!(
+            "🏷️  Tagged {} ({}) with {}",
+            t.name,
+            t.lang,
+            tags.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn remove(app_state: &AppState, cmd: &Remove) -> Result<()> {
+    let tags = valid_tags(&cmd.tags)?;
+    let cache = app_state.local_db.clone();
+
+    for t in find_templates(app_state, &cmd.template, &cmd.lang).await? {
+        let removed = match t.kind {
+            SearchResultKind::Template => cache.remove_template_tags(t.id, &tags).await?,
+            SearchResultKind::Source => cache.remove_source_template_tags(t.id, &tags).await?,
+        };
+        info!("Removed {} tag(s) from {} ({})", removed, t.name, t.lang);
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub async fn list(app_state: &AppState, cmd: &List) -> Result<()> {
+    let Some(template) = &cmd.template else {
+        let tags = app_state.local_db.list_tags().await?;
+        if !app_state.output_format.is_table() {
+            return print_serialized(app_state.output_format, &tags);
+        }
+        if tags.is_empty() {
+            info!("No tags yet.");
+            info!("💡 Have a look at `boil tag add`");
+            return Ok(());
+        }
+
+        let mut table = Table::new(&tags);
+        table.with(Style::psql());
+        print!("\n\n{table}\n\n");
+        return Ok(());
+    };
+
+    let templates = find_templates(app_state, template, &cmd.lang).await?;
+    if !app_state.output_format.is_table() {
+        return print_serialized(app_state.output_format, &templates);
+    }
+
+    let mut table = Table::new(&templates);
+    table.with(Style::psql());
+    print!("\n\n{table}\n\n");
+
+    Ok(())
+}
+
+fn valid_tags(tags: &[String]) -> Result<Vec<String>> {
+    let tags = normalize_tags(tags);
+    if tags.is_empty() {
+        return Err(eyre!("💥 No valid tags given."));
+    }
+    Ok(tags)
+}
+
+// Every lang of an installed template (by ID or name) or of a source template.
+async fn find_templates(
+    app_state: &AppState,
+    template: &str,
+    lang: &Option<String>,
+) -> Result<Vec<TaggedTemplate>> {
+    let cache = app_state.local_db.clone();
+
+    if let Some((source_name, name)) = template.split_once('/') {
+        let templates = cache
+            .find_source_templates(source_name, name, lang.clone())
+            .await?;
+        if templates.is_empty() {
+            return Err(eyre!(
+                "💥 Cannot find template `{}` in source `{}`.",
+                name,
+                source_name
+            ));
+        }
+        return Ok(templates
+            .into_iter()
+            .map(|t| TaggedTemplate {
+                kind: SearchResultKind::Source,
+                id: t.id,
+                name: format!("{}/{}", t.source_name, t.name),
+                lang: t.lang,
+                tags: t.tags,
+            })
+            .collect());
+    }
+
+    let templates = match template.parse::<i64>() {
+        Ok(id) => cache.get_template(id).await?.into_iter().collect(),
+        Err(_) => {
+            let find_params = TemplateFindParams {
+                name: Some(template.to_owned()),
+                lang: lang.clone(),
+                ..Default::default()
+            };
+            cache.find_templates(find_params).await?
+        }
+    };
+    if templates.is_empty() {
+        return Err(eyre!("💥 Cannot find template: {}.", template));
+    }
+
+    Ok(templates
+        .into_iter()
+        .map(|t| TaggedTemplate {
+            kind: SearchResultKind::Template,
+            id: t.id,
+            name: t.name,
+            lang: t.lang,
+            tags: t.tags,
+        })
+        .collect())
+}
@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use color_eyre::{Result, eyre::eyre};
+use serde::Serialize;
+use tracing::info;
+
+use crate::commands::cache::format_size;
+use crate::db::{ListTemplateOptions, Visibility};
+use crate::state::AppState;
+use crate::template::archive::ArchiveKind;
+use crate::template::pack::{PackManifest, pack_template};
+use crate::template::short_sha;
+use crate::util::output::print_serialized;
+
+#[derive(Subcommand)]
+pub enum Template {
+    #[command(about = "Validate a template and write it to a versioned archive")]
+    Pack(Pack),
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum PackFormat {
+    #[value(name = "tar.gz")]
+    TarGz,
+    Zip,
+}
+
+impl From<PackFormat> for ArchiveKind {
+    fn from(format: PackFormat) -> Self {
+        match format {
+            PackFormat::TarGz => ArchiveKind::TarGz,
+            PackFormat::Zip => ArchiveKind::Zip,
+        }
+    }
+}
+
+#[derive(Debug, Parser)]
+pub struct Pack {
+    #[arg(
+        default_value = ".",
+        help = "Template directory (with boilermaker.toml)"
+    )]
+    pub path: PathBuf,
+    #[arg(
+        short,
+        long,
+        default_value = ".",
+        help = "Directory to write the archive to"
+    )]
+    pub output: PathBuf,
+    // Not `--format`, that's the global output format.
+    #[arg(
+        short = 'f',
+        long = "archive-format",
+        value_enum,
+        default_value_t = PackFormat::TarGz
+    )]
+    pub archive_format: PackFormat,
+    #[arg(long, help = "Pack the template even if it is installed as private")]
+    pub allow_private: bool,
+}
+
+// What `boil template pack` made, printed with `--format json|yaml|csv`.
+#[derive(Debug, Serialize)]
+struct PackResult<'a> {
+    path: String,
+    size_bytes: u64,
+    files: usize,
+    #[serde(flatten)]
+    manifest: &'a PackManifest,
+}
+
+#[tracing::instrument]
+pub async fn pack(app_state: &AppState, cmd: &Pack) -> Result<()> {
+    info!("Packing template in {}", cmd.path.display());
+
+    if !cmd.allow_private {
+        ensure_not_private(app_state, &cmd.path).await?;
+    }
+
+    let packed = pack_template(&cmd.path, &cmd.output, cmd.archive_format.into())?;
+    let size = std::fs::metadata(&packed.path)?.len();
+
+    info!(
+        "Checksum: sha256:{}, commit: {}",
+        packed.manifest.checksum,
+        packed
+            .manifest
+            .commit
+            .as_deref()
+            .map(short_sha)
+            .unwrap_or_else(|| "-".to_owned())
+    );
+    info!(
+        "📦 Packed {} {} ({} file(s), {}) to {}",
+        packed.manifest.name,
+        packed.manifest.version,
+        packed.files,
+        format_size(size),
+        packed.path.display()
+    );
+
+    if !app_state.output_format.is_table() {
+        let result = PackResult {
+            path: packed.path.display().to_string(),
+            size_bytes: size,
+            files: packed.files,
+            manifest: &packed.manifest,
+        };
+        print_serialized(app_state.output_format, &result)?;
+    }
+
+    Ok(())
+}
+
+// Archives are made to be shared, so refuse dirs of private templates: the install dir of one, or
+// the local path one was installed from.
+async fn ensure_not_private(app_state: &AppState, dir: &Path) -> Result<()> {
+    let Ok(dir) = fs::canonicalize(dir) else {
+        return Ok(());
+    };
+    let opts = ListTemplateOptions {
+        visibility: Some(Visibility::Private),
+        ..Default::default()
+    };
+    let private = app_state.local_db.list_templates(Some(opts)).await?;
+    let found = private.iter().find(|t| {
+        let repo = Path::new(t.repo.strip_prefix("file://").unwrap_or(&t.repo));
+        let source_dir = match &t.subdir {
+            Some(subdir) => repo.join(subdir),
+            None => repo.to_path_buf(),
+        };
+        Path::new(&t.template_dir) == dir || source_dir == dir
+    });
+
+    match found {
+        Some(t) => Err(eyre!(
+            "💥 {} is the private template `{}`. Use `--allow-private` to pack it anyway.",
+            dir.display(),
+            t.name
+        )),
+        None => Ok(()),
+    }
+}
@@ -1,48 +1,425 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use clap::Parser;
 use color_eyre::Result;
 use color_eyre::eyre::eyre;
-use tracing::info;
+use serde::Serialize;
+use tabled::{Table, Tabled, settings::Style};
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
 
-use crate::db::TemplateRow;
-use crate::state::AppState;
-use crate::template::{
-    CloneContext, clean_dir, clone_repo, install_template, make_tmp_dir_from_url,
-};
-use crate::util::file::remove_git_dir;
+use crate::db::{TemplateFindParams, TemplateMetadata, TemplateResult, TemplateRow};
+use crate::state::{AppState, TemplateDbType};
+use crate::template::archive::{TemplateLocation, open_template};
+use crate::template::changelog::{Changelog, changelog, diverged_changelog, has_ancestor};
+use crate::template::fs::{DirFs, read_template_config, sha256_hash_fs};
+use crate::template::{CloneContext, clean_dir, install_template};
+use crate::util::crypto::sha256_hash_dir;
+use crate::util::output::print_serialized;
 
 #[derive(Debug, Parser)]
 pub struct Update {
-    #[arg(required = true)]
-    pub id: i32,
+    #[arg(
+        required_unless_present_any = ["all", "source"],
+        help = "Template ID or name"
+    )]
+    pub template: Option<String>,
+    #[arg(short, long, help = "Only update the given language of the template")]
+    pub lang: Option<String>,
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = ["template", "source"],
+        help = "Update all installed templates"
+    )]
+    pub all: bool,
+    #[arg(
+        short = 's',
+        long,
+        conflicts_with = "template",
+        help = "Update all installed templates from a source"
+    )]
+    pub source: Option<String>,
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = ["all", "source"],
+        help = "Move the template to a branch, tag or commit (required to update pinned templates)"
+    )]
+    pub to: Option<String>,
+    #[arg(
+        short,
+        long,
+        help = "Show what would change without applying the update"
+    )]
+    pub preview: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpdateStatus {
+    Updated,
+    Available,
+    Unchanged,
+    Pinned,
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdateOutcome {
+    pub status: UpdateStatus,
+    pub changelog: Option<Changelog>,
+}
+
+impl UpdateOutcome {
+    fn new(status: UpdateStatus) -> Self {
+        Self {
+            status,
+            changelog: None,
+        }
+    }
+}
+
+impl std::fmt::Display for UpdateStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateStatus::Updated => write!(f, "updated"),
+            UpdateStatus::Available => write!(f, "update available"),
+            UpdateStatus::Unchanged => write!(f, "unchanged"),
+            UpdateStatus::Pinned => write!(f, "pinned"),
+            UpdateStatus::Failed(_) => write!(f, "failed"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Tabled)]
+pub struct TabledUpdateResult {
+    pub id: i64,
+    pub name: String,
+    pub lang: String,
+    pub status: String,
+    pub detail: String,
+    // Only serialized, tables are followed by the changelogs.
+    #[tabled(skip)]
+    pub changelog: Option<Changelog>,
+}
+
+impl TabledUpdateResult {
+    pub fn from(t: &TemplateResult, outcome: &UpdateOutcome) -> Self {
+        let detail = match &outcome.status {
+            UpdateStatus::Failed(err) => err.to_owned(),
+            UpdateStatus::Pinned => "use --to <ref> to move it".to_owned(),
+            _ => "-".to_owned(),
+        };
+        Self {
+            id: t.id,
+            name: t.name.to_owned(),
+            lang: t.lang.to_owned(),
+            status: outcome.status.to_string(),
+            detail,
+            changelog: outcome.changelog.clone(),
+        }
+    }
 }
 
 #[tracing::instrument]
 pub async fn update(app_state: &AppState, cmd: &Update) -> Result<()> {
+    let templates = get_templates_to_update(app_state, cmd).await?;
+    if templates.is_empty() {
+        info!("No templates to update.");
+        return Ok(());
+    }
+
+    info!("Updating {} template(s)", templates.len());
+
+    // The langs of a template share one dir, so they're updated together in one task.
+    let mut groups: BTreeMap<String, Vec<TemplateResult>> = BTreeMap::new();
+    for templ in templates {
+        groups
+            .entry(templ.template_dir.to_owned())
+            .or_default()
+            .push(templ);
+    }
+
+    let mut tasks = JoinSet::new();
+    for (_, group) in groups {
+        let cache = app_state.local_db.clone();
+        let to = cmd.to.clone();
+        let preview = cmd.preview;
+        tasks.spawn(async move {
+            let templ = &group[0];
+            let outcome = match update_template(cache, templ, to, preview).await {
+                Ok(outcome) => outcome,
+                Err(err) => {
+                    error!("💥 Failed to update template #{}: {}", templ.id, err);
+                    UpdateOutcome::new(UpdateStatus::Failed(err.to_string()))
+                }
+            };
+            // The changelog is the same for every lang, print it once.
+            let sibling_outcome = UpdateOutcome::new(outcome.status.clone());
+            let mut results = vec![(templ.clone(), outcome)];
+            for sibling in &group[1..] {
+                results.push((sibling.clone(), sibling_outcome.clone()));
+            }
+            results
+        });
+    }
+
+    let mut results = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        results.extend(joined?);
+    }
+    results.sort_by_key(|(t, _)| t.id);
+
+    let failed = results
+        .iter()
+        .filter(|(_, o)| matches!(o.status, UpdateStatus::Failed(_)))
+        .count();
+
+    let rows = results
+        .iter()
+        .map(|(t, o)| TabledUpdateResult::from(t, o))
+        .collect::<Vec<_>>();
+    if app_state.output_format.is_table() {
+        // Print changelogs once everything is done so concurrent updates don't interleave.
+        for (t, outcome) in &results {
+            if let Some(changelog) = &outcome.changelog {
+                print!("\n📜 {} ({}): {changelog}", t.name, t.lang);
+            }
+        }
+
+        let mut table = Table::new(&rows);
+        table.with(Style::psql());
+        print!("\n\n{table}\n\n");
+    } else {
+        print_serialized(app_state.output_format, &rows)?;
+    }
+
+    if failed > 0 {
+        return Err(eyre!(
+            "💥 {} of {} template update(s) failed.",
+            failed,
+            results.len()
+        ));
+    }
+
+    if cmd.preview {
+        info!("Preview only, nothing was changed.");
+    } else {
+        info!("✅ Templates updated!");
+    }
+    Ok(())
+}
+
+async fn get_templates_to_update(
+    app_state: &AppState,
+    cmd: &Update,
+) -> Result<Vec<TemplateResult>> {
     let cache = app_state.local_db.clone();
-    let Some(templ) = cache.get_template(cmd.id as i64).await? else {
-        return Err(eyre!("💥 Cannot find template: {}.", cmd.id));
+
+    if cmd.all {
+        let templates = cache.list_templates(None).await?;
+        return Ok(filter_lang(templates, &cmd.lang));
+    }
+
+    if let Some(source) = &cmd.source {
+        let find_params = TemplateFindParams {
+            lang: cmd.lang.clone(),
+            source: Some(source.to_owned()),
+            ..Default::default()
+        };
+        return cache.find_templates(find_params).await;
+    }
+
+    let Some(template) = &cmd.template else {
+        return Err(eyre!("💥 Pass a template ID or name, --all or --source."));
+    };
+
+    // Keep `boil update <id>` working alongside lookups by name.
+    if let Ok(id) = template.parse::<i64>() {
+        let Some(templ) = cache.get_template(id).await? else {
+            return Err(eyre!("💥 Cannot find template: {}.", id));
+        };
+        return Ok(vec![templ]);
+    }
+
+    let find_params = TemplateFindParams {
+        name: Some(template.to_owned()),
+        lang: cmd.lang.clone(),
+        ..Default::default()
     };
+    let templates = cache.find_templates(find_params).await?;
+    if templates.is_empty() {
+        return Err(eyre!("💥 Cannot find template: {}.", template));
+    }
+
+    Ok(templates)
+}
+
+fn filter_lang(templates: Vec<TemplateResult>, lang: &Option<String>) -> Vec<TemplateResult> {
+    match lang {
+        Some(lang) => templates.into_iter().filter(|t| &t.lang == lang).collect(),
+        None => templates,
+    }
+}
+
+#[tracing::instrument(skip(cache))]
+pub async fn update_template(
+    cache: TemplateDbType,
+    templ: &TemplateResult,
+    to: Option<String>,
+    preview: bool,
+) -> Result<UpdateOutcome> {
+    if templ.pinned && to.is_none() {
+        info!("Template #{} is pinned, skipping: {}", templ.id, templ.name);
+        return Ok(UpdateOutcome::new(UpdateStatus::Pinned));
+    }
 
     info!("Updating template #{}: {}", templ.id, templ.name);
 
     let template_dir = PathBuf::from(templ.template_dir.clone());
-    let tmp_clone_dir = make_tmp_dir_from_url(&templ.repo);
-
-    let clone_ctx = CloneContext::new(
-        &templ.repo,
-        Some(tmp_clone_dir.clone()),
-        templ.branch.clone(),
-    );
-    clone_repo(&clone_ctx).await?;
+
+    let location = TemplateLocation::detect(&templ.repo);
+    // Unpinned templates follow the branch they were installed from.
+    let git_ref = to.or_else(|| templ.git_ref.clone());
+    let clone_ctx = CloneContext::new(&templ.repo, None, templ.branch.clone())
+        .with_ref(git_ref)
+        .with_subdir(templ.subdir.clone());
+    let opened = open_template(&location, &clone_ctx).await?;
+    let commit_sha = opened.commit_sha();
+    let config = read_template_config(opened.fs.as_ref())?;
+    let metadata = TemplateMetadata::from(&config.project);
+    let author_tags = config.project.tags.unwrap_or_default();
+    let cloned = opened.cloned;
+
+    let unchanged = match (&templ.commit_sha, &cloned) {
+        (Some(sha), Some(cloned)) => {
+            sha == &cloned.commit_sha && templ.git_ref.as_ref() == Some(&cloned.git_ref)
+        }
+        // Local dirs, archives and templates installed before commits were tracked can only be
+        // compared by content.
+        _ => {
+            template_dir.exists()
+                && sha256_hash_fs(opened.fs.as_ref())? == sha256_hash_dir(&template_dir)?
+        }
+    };
+
+    let row = match &cloned {
+        Some(cloned) => TemplateRow {
+            git_ref: Some(cloned.git_ref.to_owned()),
+            commit_sha: Some(cloned.commit_sha.to_owned()),
+            pinned: cloned.ref_kind.is_pinned(),
+            metadata: metadata.clone(),
+            ..TemplateRow::from(templ.clone())
+        },
+        None => TemplateRow {
+            commit_sha,
+            metadata: metadata.clone(),
+            ..TemplateRow::from(templ.clone())
+        },
+    };
+
+    // Other langs installed into the same dir move along with this one.
+    let siblings = find_siblings(&cache, templ).await?;
+    let sibling_row = |sibling: &TemplateResult| TemplateRow {
+        git_ref: row.git_ref.clone(),
+        commit_sha: row.commit_sha.clone(),
+        pinned: row.pinned,
+        metadata: metadata.clone(),
+        ..TemplateRow::from(sibling.clone())
+    };
+
+    if unchanged && template_dir.exists() {
+        // Record the commit and metadata for templates that were installed before they were
+        // tracked.
+        let untracked = templ.commit_sha.is_none() && cloned.is_some();
+        if (untracked || templ.metadata != metadata) && !preview {
+            for sibling in &siblings {
+                cache
+                    .update_template(sibling.id, sibling_row(sibling))
+                    .await?;
+            }
+            cache.update_template(templ.id, row).await?;
+        }
+        if !preview {
+            sync_author_tags(&cache, templ, &siblings, &author_tags).await?;
+        }
+        return Ok(UpdateOutcome::new(UpdateStatus::Unchanged));
+    }
+
+    let changelog = match &cloned {
+        Some(cloned) if templ.commit_sha.as_ref() != Some(&cloned.commit_sha) => {
+            let to = &cloned.commit_sha;
+            let built = match templ.commit_sha.as_deref() {
+                Some(from) if has_ancestor(&cloned.repo, to, from) => {
+                    changelog(&cloned.repo, from, to, templ.subdir.as_deref())
+                }
+                // Force-pushed, or installed before commits were tracked.
+                from => {
+                    diverged_changelog(from, to, &DirFs::new(&template_dir), opened.fs.as_ref())
+                }
+            };
+            match built {
+                Ok(changelog) => Some(changelog),
+                Err(err) => {
+                    warn!("Can't build changelog for template #{}: {}", templ.id, err);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+
+    if preview {
+        return Ok(UpdateOutcome {
+            status: UpdateStatus::Available,
+            changelog,
+        });
+    }
+
     clean_dir(&template_dir)?;
-    install_template(&tmp_clone_dir, &template_dir).await?;
-    remove_git_dir(&template_dir)?;
+    install_template(opened.fs.as_ref(), &template_dir).await?;
 
-    let row = TemplateRow::from(templ.clone());
+    for sibling in &siblings {
+        cache
+            .update_template(sibling.id, sibling_row(sibling))
+            .await?;
+        cache.index_template(sibling.id).await?;
+    }
     cache.update_template(templ.id, row).await?;
+    cache.index_template(templ.id).await?;
+    sync_author_tags(&cache, templ, &siblings, &author_tags).await?;
+
+    Ok(UpdateOutcome {
+        status: UpdateStatus::Updated,
+        changelog,
+    })
+}
 
-    info!("✅ Template updated!");
+// The tags of boilermaker.toml may have changed along with the template.
+async fn sync_author_tags(
+    cache: &TemplateDbType,
+    templ: &TemplateResult,
+    siblings: &[TemplateResult],
+    tags: &[String],
+) -> Result<()> {
+    for id in siblings.iter().map(|s| s.id).chain([templ.id]) {
+        cache.set_author_template_tags(id, tags).await?;
+    }
     Ok(())
 }
+
+async fn find_siblings(
+    cache: &TemplateDbType,
+    templ: &TemplateResult,
+) -> Result<Vec<TemplateResult>> {
+    let find_params = TemplateFindParams {
+        name: Some(templ.name.to_owned()),
+        ..Default::default()
+    };
+    let templates = cache.find_templates(find_params).await?;
+    Ok(templates
+        .into_iter()
+        .filter(|t| t.id != templ.id && t.template_dir == templ.template_dir)
+        .collect())
+}
@@ -8,8 +8,8 @@ use std::{
 use color_eyre::eyre::{Error, Result, eyre};
 use dirs::home_dir;
 use lazy_static::lazy_static;
-use serde::Deserialize;
 use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use tracing::{info, warn};
 
@@ -39,6 +39,8 @@ pub fn make_default_config() -> SysConfig {
     SysConfig {
         log_level: Some("INFO".to_string()),
         sources: None,
+        git: None,
+        presets: None,
     }
 }
 
@@ -67,6 +69,68 @@ pub fn get_system_config_path(config_path: Option<&Path>) -> Result<Option<&Path
 pub struct SysConfig {
     pub log_level: Option<String>,
     pub sources: Option<Vec<HashMap<String, String>>>,
+    pub git: Option<GitConfig>,
+    pub presets: Option<HashMap<String, UserPreset>>,
+}
+
+// A template with the lang and variables to make projects with, used as `boil new @<alias>`, e.g.
+//
+//   [presets.our-service]
+//   template = "api"
+//   lang = "rust"
+//   preset = "full"  # one of the template's presets
+//   vars = { org = "acme", license = "MIT" }
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserPreset {
+    pub template: String,
+    pub lang: Option<String>,
+    pub preset: Option<String>,
+    pub vars: Option<HashMap<String, toml::Value>>,
+}
+
+impl UserPreset {
+    pub fn vars(&self) -> HashMap<String, String> {
+        self.vars
+            .clone()
+            .map(value_map_to_strings)
+            .unwrap_or_default()
+    }
+}
+
+// Credentials for private template repos, e.g.
+//
+//   [git]
+//   ssh_keys = ["~/.ssh/id_work"]
+//
+//   [git.hosts."github.com"]
+//   token_env = "WORK_GITHUB_TOKEN"
+//
+//   [git.hosts."git.internal.example"]
+//   private = true  # templates from here are installed as private
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct GitConfig {
+    pub ssh_keys: Option<Vec<String>>,
+    pub hosts: Option<HashMap<String, GitHostConfig>>,
+}
+
+#[derive(Default, Clone, Deserialize)]
+pub struct GitHostConfig {
+    pub username: Option<String>,
+    pub token: Option<String>,
+    pub token_env: Option<String>,
+    pub private: Option<bool>,
+}
+
+// Keep tokens out of debug output and traces.
+impl fmt::Debug for GitHostConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitHostConfig")
+            .field("username", &self.username)
+            .field("token", &self.token.as_ref().map(|_| "***"))
+            .field("token_env", &self.token_env)
+            .field("private", &self.private)
+            .finish()
+    }
 }
 
 //TODO: add ability for config to be in YAML as well as TOML
@@ -133,28 +197,76 @@ pub struct TemplateConfig {
     // pub variables: Option<toml::Value>,
     //pub variables: Option<HashMap<String, String>>,
     pub variables: Option<TemplateConfigVariableMap>,
+    // Named sets of variable values, e.g. `[presets.minimal]`, used with `boil new --preset`.
+    pub presets: Option<HashMap<String, HashMap<String, toml::Value>>>,
 }
 
-#[derive(Debug, Deserialize)]
+impl TemplateConfig {
+    pub fn preset_names(&self) -> Vec<String> {
+        let mut names = self
+            .presets
+            .as_ref()
+            .map(|presets| presets.keys().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    pub fn preset(&self, name: &str) -> Option<HashMap<String, String>> {
+        self.presets
+            .as_ref()?
+            .get(name)
+            .cloned()
+            .map(value_map_to_strings)
+    }
+}
+
+// Variables are strings, other TOML values are written as they are in the file.
+fn value_to_string(value: toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s,
+        other_type => other_type.to_string(),
+    }
+}
+
+fn value_map_to_strings(values: HashMap<String, toml::Value>) -> HashMap<String, String> {
+    values
+        .into_iter()
+        .map(|(name, value)| (name, value_to_string(value)))
+        .collect()
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 pub struct TemplateConfigProject {
-    // pub name: String,
-    // pub repository: String,
+    pub name: Option<String>,
+    pub repository: Option<String>,
     // pub subdir: Option<String>,
-    // pub version: Option<String>,
+    pub version: Option<String>,
     pub default_lang: Option<String>,
-    // pub description: Option<String>,
-    // pub authors: Option<Vec<String>>,
-    // pub license: Option<String>,
-    // pub keywords: Option<Vec<String>>,
-    // pub website: Option<String>,
+    pub description: Option<String>,
+    pub authors: Option<Vec<String>>,
+    pub license: Option<String>,
+    pub keywords: Option<Vec<String>>,
+    // Categories like `backend` or `cli`, for `boil list --tag` and `tag:` searches.
+    pub tags: Option<Vec<String>>,
+    pub website: Option<String>,
 }
 
+// Variables are either `name = "default"` or `name = { default = "...", description = "..." }`,
+// where both keys are optional.
 #[derive(Debug)]
-pub struct TemplateConfigVariableMap(HashMap<String, String>);
+pub struct TemplateConfigVariableMap {
+    defaults: HashMap<String, String>,
+    descriptions: HashMap<String, String>,
+}
 
 impl TemplateConfigVariableMap {
     pub fn as_map(&self) -> &HashMap<String, String> {
-        &self.0
+        &self.defaults
+    }
+
+    pub fn description(&self, name: &str) -> Option<&str> {
+        self.descriptions.get(name).map(|d| d.as_str())
     }
 }
 
@@ -180,15 +292,41 @@ impl<'de> Visitor<'de> for TemplateConfigVariableMapVisitor {
     where
         M: MapAccess<'de>,
     {
-        let mut m = HashMap::new();
+        let mut defaults = HashMap::new();
+        let mut descriptions = HashMap::new();
         while let Some(key) = map.next_key::<String>()? {
             let value: toml::Value = map.next_value()?;
-            let s = match value {
-                toml::Value::String(s) => s,
-                other_type => other_type.to_string(),
+            let value = match value {
+                // Without a `default`, the variable defaults to an empty string.
+                toml::Value::Table(mut table) => {
+                    match table.remove("description") {
+                        Some(toml::Value::String(description)) => {
+                            descriptions.insert(key.clone(), description);
+                        }
+                        Some(_) => {
+                            return Err(de::Error::custom(format!(
+                                "the description of variable `{key}` must be a string"
+                            )));
+                        }
+                        None => {}
+                    }
+                    if let Some(unknown) = table.keys().find(|k| *k != "default") {
+                        return Err(de::Error::custom(format!(
+                            "unknown key `{unknown}` in variable `{key}`, expected `default` or \
+                             `description`"
+                        )));
+                    }
+                    table
+                        .remove("default")
+                        .unwrap_or_else(|| toml::Value::String(String::new()))
+                }
+                other => other,
             };
-            m.insert(key, s);
+            defaults.insert(key, value_to_string(value));
         }
-        Ok(TemplateConfigVariableMap(m))
+        Ok(TemplateConfigVariableMap {
+            defaults,
+            descriptions,
+        })
     }
 }
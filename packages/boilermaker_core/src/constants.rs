@@ -3,8 +3,12 @@ use regex::Regex;
 
 pub const BRANCH_REGEX: &str = r"^(refs/heads/)?[A-Za-z0-9._/-]+$";
 pub const SUBDIR_REGEX: &str = r"^/?[A-Za-z0-9/\-_].*$";
+// Variable names whose values aren't written to the generation history.
+pub const SECRET_VARIABLE_REGEX: &str =
+    r"(?i)(passw(or)?d|secret|token|api_?key|private_?key|credential)";
 
 lazy_static! {
     pub static ref BRANCH_PATTERN: Regex = Regex::new(BRANCH_REGEX).unwrap();
     pub static ref SUBDIR_PATTERN: Regex = Regex::new(SUBDIR_REGEX).unwrap();
+    pub static ref SECRET_VARIABLE_PATTERN: Regex = Regex::new(SECRET_VARIABLE_REGEX).unwrap();
 }
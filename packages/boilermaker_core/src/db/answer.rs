@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+
+use color_eyre::Result;
+
+use super::{LocalCache, is_secret_variable};
+
+#[async_trait::async_trait]
+pub trait AnswerMethods: Send + Sync {
+    async fn get_answers(&self, template: &str) -> Result<BTreeMap<String, String>>;
+    async fn save_answers(&self, template: &str, answers: &BTreeMap<String, String>) -> Result<()>;
+}
+
+#[async_trait::async_trait]
+impl AnswerMethods for LocalCache {
+    #[tracing::instrument]
+    async fn get_answers(&self, template: &str) -> Result<BTreeMap<String, String>> {
+        let rows = sqlx::query_as::<_, (String, String)>(
+            "SELECT name, value FROM answer WHERE template = ?;",
+        )
+        .bind(template)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    // Answers for other variables of the template are kept, secrets are skipped.
+    #[tracing::instrument(skip(answers))]
+    async fn save_answers(&self, template: &str, answers: &BTreeMap<String, String>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for (name, value) in answers.iter().filter(|(name, _)| !is_secret_variable(name)) {
+            sqlx::query(
+                r#"
+                INSERT INTO answer (template, name, value, updated_at)
+                VALUES (?, ?, ?, strftime('%s','now'))
+                ON CONFLICT (template, name)
+                DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at;
+                "#,
+            )
+            .bind(template)
+            .bind(name)
+            .bind(value)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
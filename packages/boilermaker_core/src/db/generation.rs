@@ -0,0 +1,228 @@
+use std::collections::BTreeMap;
+
+use color_eyre::Result;
+use serde::{Serialize, Serializer};
+use sqlx::QueryBuilder;
+use tabled::Tabled;
+
+use super::LocalCache;
+use crate::constants::SECRET_VARIABLE_PATTERN;
+use crate::template as tmpl;
+use crate::util::time::timestamp_to_iso8601;
+
+#[async_trait::async_trait]
+pub trait GenerationMethods: Send + Sync {
+    async fn create_generation(&self, row: GenerationRow) -> Result<i64>;
+    async fn get_generation(&self, id: i64) -> Result<Option<GenerationResult>>;
+    async fn list_generations(&self, opts: ListGenerationOptions) -> Result<Vec<GenerationResult>>;
+}
+
+#[async_trait::async_trait]
+impl GenerationMethods for LocalCache {
+    #[tracing::instrument]
+    async fn create_generation(&self, row: GenerationRow) -> Result<i64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO generation
+              (template_id, template, name, lang, repo, branch, subdir, git_ref, commit_sha,
+               template_hash, project, output_dir, variables, created_at, duration_ms)
+            VALUES
+              (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, strftime('%s','now'), ?);
+            "#,
+        )
+        .bind(row.template_id)
+        .bind(&row.template)
+        .bind(&row.name)
+        .bind(&row.lang)
+        .bind(&row.repo)
+        .bind(&row.branch)
+        .bind(&row.subdir)
+        .bind(&row.git_ref)
+        .bind(&row.commit_sha)
+        .bind(&row.template_hash)
+        .bind(&row.project)
+        .bind(&row.output_dir)
+        .bind(serde_json::to_string(&row.variables)?)
+        .bind(row.duration_ms)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.last_insert_rowid())
+    }
+
+    #[tracing::instrument]
+    async fn get_generation(&self, id: i64) -> Result<Option<GenerationResult>> {
+        let result =
+            sqlx::query_as::<_, GenerationResult>("SELECT * FROM generation WHERE id = ?;")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(result)
+    }
+
+    // Newest first. `query` matches the project, output dir, template and variables.
+    #[tracing::instrument]
+    async fn list_generations(&self, opts: ListGenerationOptions) -> Result<Vec<GenerationResult>> {
+        let mut qb = QueryBuilder::new("SELECT * FROM generation WHERE 1=1");
+
+        if let Some(query) = opts.query {
+            let pattern = format!("%{query}%");
+            qb.push(" AND (project LIKE ");
+            qb.push_bind(pattern.clone());
+            qb.push(" OR output_dir LIKE ");
+            qb.push_bind(pattern.clone());
+            qb.push(" OR template LIKE ");
+            qb.push_bind(pattern.clone());
+            qb.push(" OR name LIKE ");
+            qb.push_bind(pattern.clone());
+            qb.push(" OR variables LIKE ");
+            qb.push_bind(pattern);
+            qb.push(")");
+        }
+        if let Some(name) = opts.name {
+            qb.push(" AND name = ");
+            qb.push_bind(name);
+        }
+        if let Some(lang) = opts.lang {
+            qb.push(" AND lang = ");
+            qb.push_bind(lang);
+        }
+        qb.push(" ORDER BY created_at DESC, id DESC");
+        if let Some(limit) = opts.limit {
+            qb.push(" LIMIT ");
+            qb.push_bind(limit as i64);
+        }
+
+        let results = qb
+            .build_query_as::<GenerationResult>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(results)
+    }
+}
+
+// Stored in place of variables that look like secrets (`db_password`, `api_token`, ...).
+pub const REDACTED: &str = "<redacted>";
+
+pub fn is_secret_variable(name: &str) -> bool {
+    SECRET_VARIABLE_PATTERN.is_match(name)
+}
+
+pub fn redact_secrets(variables: BTreeMap<String, String>) -> BTreeMap<String, String> {
+    variables
+        .into_iter()
+        .map(|(name, value)| {
+            if is_secret_variable(&name) {
+                (name, REDACTED.to_owned())
+            } else {
+                (name, value)
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct GenerationRow {
+    // The installed template, `None` for source templates, URLs and paths.
+    pub template_id: Option<i64>,
+    // The name or `<source>/<template>` passed to `boil new`, or the URL or absolute path of the
+    // template.
+    pub template: String,
+    pub name: String,
+    pub lang: String,
+    pub repo: Option<String>,
+    pub branch: Option<String>,
+    pub subdir: Option<String>,
+    pub git_ref: Option<String>,
+    pub commit_sha: Option<String>,
+    pub template_hash: Option<String>,
+    pub project: String,
+    pub output_dir: String,
+    // Secrets already redacted.
+    pub variables: BTreeMap<String, String>,
+    pub duration_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct GenerationResult {
+    pub id: i64,
+    pub template_id: Option<i64>,
+    pub template: String,
+    pub name: String,
+    pub lang: String,
+    pub repo: Option<String>,
+    pub branch: Option<String>,
+    pub subdir: Option<String>,
+    pub git_ref: Option<String>,
+    pub commit_sha: Option<String>,
+    pub template_hash: Option<String>,
+    pub project: String,
+    pub output_dir: String,
+    // A JSON object.
+    #[serde(serialize_with = "serialize_variables")]
+    pub variables: String,
+    pub created_at: i64,
+    pub duration_ms: i64,
+}
+
+impl GenerationResult {
+    pub fn variables(&self) -> Result<BTreeMap<String, String>> {
+        Ok(serde_json::from_str(&self.variables)?)
+    }
+}
+
+// Written out as an object rather than the JSON string it's stored as.
+fn serialize_variables<S: Serializer>(variables: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    let value: serde_json::Value =
+        serde_json::from_str(variables).map_err(serde::ser::Error::custom)?;
+    value.serialize(serializer)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ListGenerationOptions {
+    pub query: Option<String>,
+    // Template name.
+    pub name: Option<String>,
+    pub lang: Option<String>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Tabled)]
+pub struct TabledGenerationResult {
+    pub id: i64,
+    pub project: String,
+    pub template: String,
+    pub lang: String,
+    pub commit: String,
+    pub output_dir: String,
+    pub created_at: String,
+    pub duration: String,
+}
+
+impl TabledGenerationResult {
+    pub fn from(row: GenerationResult) -> Self {
+        Self {
+            id: row.id,
+            project: row.project,
+            template: row.template,
+            lang: row.lang,
+            commit: row
+                .commit_sha
+                .map(|sha| tmpl::short_sha(&sha))
+                .unwrap_or_else(|| "-".to_string()),
+            output_dir: row.output_dir,
+            created_at: timestamp_to_iso8601(row.created_at),
+            duration: format_duration_ms(row.duration_ms),
+        }
+    }
+}
+
+pub fn format_duration_ms(ms: i64) -> String {
+    if ms < 1000 {
+        format!("{ms}ms")
+    } else {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    }
+}
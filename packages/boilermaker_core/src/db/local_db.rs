@@ -4,12 +4,14 @@ use sqlx::{
     sqlite::{SqliteConnectOptions, SqlitePool},
 };
 
-use super::{SourceMethods, TemplateMethods};
+use super::{AnswerMethods, GenerationMethods, SourceMethods, TagMethods, TemplateMethods};
 
 static MIGRATOR: Migrator = sqlx::migrate!("../../migrations");
 
 #[async_trait::async_trait]
-pub trait TemplateDb: TemplateMethods + SourceMethods + Send + Sync {
+pub trait TemplateDb:
+    TemplateMethods + SourceMethods + TagMethods + GenerationMethods + AnswerMethods + Send + Sync
+{
     // TODO: rename create_schema or similar (now local_db has cache + sources)
     async fn create_schema(&self) -> Result<()>;
 }
@@ -1,7 +1,15 @@
+pub mod answer;
+pub mod generation;
 pub mod local_db;
+pub mod query;
 pub mod source;
+pub mod tag;
 pub mod template;
 
+pub use answer::*;
+pub use generation::*;
 pub use local_db::*;
+pub use query::*;
 pub use source::*;
+pub use tag::*;
 pub use template::*;
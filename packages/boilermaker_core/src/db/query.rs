@@ -0,0 +1,319 @@
+use color_eyre::{Result, eyre::eyre};
+
+use super::{Visibility, normalize_tag};
+
+// A parsed search, e.g. `axum "web server" lang:rust NOT actix`.
+//
+// Free text is turned into an FTS5 expression in which every term is quoted, so punctuation like
+// `foo-bar` or `c++` can't break the query. Supported syntax:
+// - `word`, `"a phrase"` and prefixes `word*` / `"a phr"*`
+// - `AND`, `OR` and `NOT` between terms (uppercase, like FTS5), terms without one are ANDed.
+//   `NOT` needs a term on both sides. Like every term it's matched per indexed row (the metadata of
+//   a template, or one of its files), so `axum NOT actix` still finds a template that mentions
+//   actix in one file and axum in another.
+// - field filters `lang:rust`, `name:axum`, `path:Dockerfile`, `source:team` and `tag:backend`,
+//   which may be quoted (`name:"my app"`). Repeating a field matches any of its values, except
+//   `tag:` where every tag has to match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    // FTS5 expression of the free text, `None` when the search only has filters.
+    pub fts: Option<String>,
+    // The searched words and phrases, without operators. Used for suggestions.
+    pub words: Vec<String>,
+    pub langs: Vec<String>,
+    pub names: Vec<String>,
+    pub paths: Vec<String>,
+    pub sources: Vec<String>,
+    pub tags: Vec<String>,
+    // Set by the caller (`--public`/`--private`), not part of the syntax.
+    pub visibility: Option<Visibility>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Term { text: String, prefix: bool },
+    Field { name: String, value: String },
+    And,
+    Or,
+    Not,
+}
+
+const FIELDS: [&str; 5] = ["lang", "name", "path", "source", "tag"];
+
+impl SearchQuery {
+    #[tracing::instrument]
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut query = SearchQuery::default();
+        let mut terms: Vec<Token> = Vec::new();
+
+        for token in tokenize(input) {
+            match token {
+                Token::Field { name, value } => match name.as_str() {
+                    "lang" => query.langs.push(value.to_lowercase()),
+                    "name" => query.names.push(value),
+                    "path" => query.paths.push(value),
+                    "source" => query.sources.push(value),
+                    // Tags are stored normalized, a value with nothing left can't match.
+                    "tag" => query
+                        .tags
+                        .push(normalize_tag(&value).unwrap_or(value.to_lowercase())),
+                    _ => unreachable!("unknown fields are parsed as terms"),
+                },
+                token => terms.push(token),
+            }
+        }
+
+        query.fts = fts_expression(&terms)?;
+        query.words = terms
+            .iter()
+            .filter_map(|t| match t {
+                Token::Term { text, .. } if text.chars().any(char::is_alphanumeric) => {
+                    Some(text.to_owned())
+                }
+                _ => None,
+            })
+            .collect();
+        if query.is_empty() {
+            return Err(eyre!("💥 Nothing to search for in `{}`.", input.trim()));
+        }
+        Ok(query)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fts.is_none()
+            && self.langs.is_empty()
+            && self.names.is_empty()
+            && self.paths.is_empty()
+            && self.sources.is_empty()
+            && self.tags.is_empty()
+    }
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        if c == '"' {
+            chars.next();
+            let text = read_quoted(&mut chars);
+            let prefix = chars.next_if_eq(&'*').is_some();
+            tokens.push(Token::Term { text, prefix });
+            continue;
+        }
+
+        let mut word = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            word.push(c);
+            // `field:"quoted value"`
+            if c == ':' && FIELDS.contains(&&word[..word.len() - 1]) && chars.peek() == Some(&'"') {
+                chars.next();
+                let value = read_quoted(&mut chars);
+                word.push_str(&value);
+                break;
+            }
+        }
+        tokens.push(word_token(word));
+    }
+
+    tokens
+}
+
+// Read up to the closing quote (or the end of the input). `""` is an escaped quote.
+fn read_quoted(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut text = String::new();
+    while let Some(c) = chars.next() {
+        if c == '"' {
+            if chars.next_if_eq(&'"').is_some() {
+                text.push('"');
+                continue;
+            }
+            break;
+        }
+        text.push(c);
+    }
+    text
+}
+
+fn word_token(word: String) -> Token {
+    match word.as_str() {
+        "AND" => return Token::And,
+        "OR" => return Token::Or,
+        "NOT" => return Token::Not,
+        _ => {}
+    }
+
+    if let Some((name, value)) = word.split_once(':')
+        && FIELDS.contains(&name)
+        && !value.is_empty()
+    {
+        return Token::Field {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        };
+    }
+
+    match word.strip_suffix('*') {
+        Some(text) if !text.is_empty() => Token::Term {
+            text: text.to_owned(),
+            prefix: true,
+        },
+        _ => Token::Term {
+            text: word,
+            prefix: false,
+        },
+    }
+}
+
+// Join the terms with their operators. `AND` and `OR` without a term on both sides are dropped, as
+// are terms FTS5 would find no words in (like `-`). A dangling `NOT` is an error: dropping it would
+// search for what was meant to be excluded.
+fn fts_expression(tokens: &[Token]) -> Result<Option<String>> {
+    let mut parts: Vec<String> = Vec::new();
+    let mut pending_op: Option<&str> = None;
+    let dangling_not = || eyre!("💥 `NOT` needs a term on both sides, e.g. `axum NOT actix`.");
+
+    for token in tokens {
+        match token {
+            Token::Term { text, prefix } => {
+                if !text.chars().any(char::is_alphanumeric) {
+                    continue;
+                }
+                if !parts.is_empty() {
+                    parts.push(pending_op.take().unwrap_or("AND").to_owned());
+                }
+                let star = if *prefix { "*" } else { "" };
+                parts.push(format!("\"{}\"{star}", text.replace('"', "\"\"")));
+            }
+            Token::Not if parts.is_empty() => return Err(dangling_not()),
+            _ if pending_op == Some("NOT") => return Err(dangling_not()),
+            Token::And if !parts.is_empty() => pending_op = Some("AND"),
+            Token::Or if !parts.is_empty() => pending_op = Some("OR"),
+            Token::Not => pending_op = Some("NOT"),
+            _ => {}
+        }
+    }
+    if pending_op == Some("NOT") {
+        return Err(dangling_not());
+    }
+
+    Ok((!parts.is_empty()).then(|| parts.join(" ")))
+}
+
+// A query for the trigram indexes that matches everything sharing at least one trigram with
+// `term`, so misspelled names still find candidates. `None` for terms under 3 characters.
+pub fn trigram_query(term: &str) -> Option<String> {
+    let chars: Vec<char> = term.trim().to_lowercase().chars().collect();
+    let mut trigrams: Vec<String> = chars
+        .windows(3)
+        .map(|w| format!("\"{}\"", w.iter().collect::<String>().replace('"', "\"\"")))
+        .collect();
+    trigrams.dedup();
+    (!trigrams.is_empty()).then(|| trigrams.join(" OR "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fts(input: &str) -> Option<String> {
+        SearchQuery::parse(input).unwrap().fts
+    }
+
+    #[test]
+    fn quotes_every_term() {
+        assert_eq!(fts("axum").as_deref(), Some(r#""axum""#));
+        assert_eq!(fts("c++").as_deref(), Some(r#""c++""#));
+        assert_eq!(fts("foo-bar").as_deref(), Some(r#""foo-bar""#));
+        assert_eq!(fts("axum tokio").as_deref(), Some(r#""axum" AND "tokio""#));
+    }
+
+    #[test]
+    fn phrases_and_embedded_quotes() {
+        assert_eq!(fts(r#""web server""#).as_deref(), Some(r#""web server""#));
+        assert_eq!(fts(r#""say ""hi""""#).as_deref(), Some(r#""say ""hi""""#));
+        assert_eq!(fts(r#"say"hi"#).as_deref(), Some(r#""say""hi""#));
+        // An unclosed quote runs to the end.
+        assert_eq!(fts(r#""web serv"#).as_deref(), Some(r#""web serv""#));
+    }
+
+    #[test]
+    fn prefixes() {
+        assert_eq!(fts("ax*").as_deref(), Some(r#""ax"*"#));
+        assert_eq!(fts(r#""web ser"*"#).as_deref(), Some(r#""web ser"*"#));
+        assert!(SearchQuery::parse("*").is_err());
+    }
+
+    #[test]
+    fn field_filters() {
+        let q = SearchQuery::parse(
+            r#"axum lang:Rust name:"my app" path:Dockerfile source:team tag:"Web Server""#,
+        )
+        .unwrap();
+        assert_eq!(q.fts.as_deref(), Some(r#""axum""#));
+        assert_eq!(q.words, vec!["axum"]);
+        assert_eq!(q.langs, vec!["rust"]);
+        assert_eq!(q.names, vec!["my app"]);
+        assert_eq!(q.paths, vec!["Dockerfile"]);
+        assert_eq!(q.sources, vec!["team"]);
+        assert_eq!(q.tags, vec!["web-server"]);
+
+        let q = SearchQuery::parse("lang:rust lang:go").unwrap();
+        assert_eq!(q.fts, None);
+        assert_eq!(q.langs, vec!["rust", "go"]);
+
+        // Unknown fields and empty values are plain terms.
+        assert_eq!(fts("foo:bar").as_deref(), Some(r#""foo:bar""#));
+        assert_eq!(fts("lang:").as_deref(), Some(r#""lang:""#));
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(
+            fts("axum OR actix").as_deref(),
+            Some(r#""axum" OR "actix""#)
+        );
+        assert_eq!(
+            fts("axum NOT actix").as_deref(),
+            Some(r#""axum" NOT "actix""#)
+        );
+        assert_eq!(
+            fts("lang:rust axum NOT actix").as_deref(),
+            Some(r#""axum" NOT "actix""#)
+        );
+        // Lowercase operators are words.
+        assert_eq!(fts("axum or").as_deref(), Some(r#""axum" AND "or""#));
+        // Dangling AND/OR are dropped.
+        assert_eq!(fts("OR axum AND").as_deref(), Some(r#""axum""#));
+        assert_eq!(
+            fts("axum - tokio").as_deref(),
+            Some(r#""axum" AND "tokio""#)
+        );
+    }
+
+    #[test]
+    fn dangling_not_is_an_error() {
+        for input in [
+            "NOT actix",
+            "lang:rust NOT actix",
+            "axum NOT",
+            "axum NOT -",
+            "axum NOT OR actix",
+            "AND NOT actix",
+        ] {
+            assert!(SearchQuery::parse(input).is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn empty_queries_are_errors() {
+        assert!(SearchQuery::parse("").is_err());
+        assert!(SearchQuery::parse("  AND OR ").is_err());
+        assert!(SearchQuery::parse("- +").is_err());
+    }
+}
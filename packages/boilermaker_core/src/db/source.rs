@@ -1,24 +1,36 @@
 use std::collections::HashMap;
-use std::path::PathBuf;
 
 use color_eyre::{Result, eyre::eyre};
+use serde::Serialize;
 use tabled::Tabled;
 use unicode_truncate::{Alignment, UnicodeTruncateStr};
 
 use crate::template as tmpl;
+use crate::template::fs::TemplateFs;
 use crate::util::crypto::sha256_hash_string;
-use crate::util::file::read_file_to_string;
 
-use super::LocalCache;
+use super::{
+    LocalCache, SOURCE_TEMPLATE_TAGS, TagOrigin, TemplateMetadata, Visibility, insert_tags,
+};
 
 #[async_trait::async_trait]
 pub trait SourceMethods: Send + Sync {
     async fn add_source(
         &self,
         source_row: SourceRow,
-        partial_source_template_rows: Vec<(PathBuf, PartialSourceTemplateRow)>,
+        partial_source_template_rows: Vec<(Box<dyn TemplateFs>, PartialSourceTemplateRow)>,
     ) -> Result<AddSourceResult>;
     async fn list_sources(&self) -> Result<Vec<SourceRow>>;
+    async fn find_source_templates(
+        &self,
+        source_name: &str,
+        template_name: &str,
+        lang: Option<String>,
+    ) -> Result<Vec<SourceTemplateResult>>;
+    async fn get_source_template_content(
+        &self,
+        source_template_id: i64,
+    ) -> Result<Vec<SourceTemplateContentRow>>;
     //async fn search_sources(&self, term: &str) -> Result<Vec<SourceRow>>;
 }
 
@@ -29,7 +41,7 @@ impl SourceMethods for LocalCache {
     async fn add_source(
         &self,
         source_row: SourceRow,
-        partial_source_template_rows: Vec<(PathBuf, PartialSourceTemplateRow)>,
+        partial_source_template_rows: Vec<(Box<dyn TemplateFs>, PartialSourceTemplateRow)>,
     ) -> Result<AddSourceResult> {
         let mut tx = self.pool.begin().await?;
 
@@ -52,7 +64,7 @@ impl SourceMethods for LocalCache {
         let source_id = source_result.last_insert_rowid();
 
         let mut source_template_ids: Vec<i64> = Vec::new();
-        for (path, partial) in partial_source_template_rows.into_iter() {
+        for (template_fs, partial) in partial_source_template_rows.into_iter() {
             let source_template_row = SourceTemplateRow {
                 source_id,
                 repo: partial.repo,
@@ -61,15 +73,19 @@ impl SourceMethods for LocalCache {
                 branch: partial.branch,
                 subdir: partial.subdir,
                 sha256_hash: None,
+                metadata: partial.metadata,
+                visibility: partial.visibility,
             }
             .set_hash_string();
 
             let template_result = sqlx::query(
                 r#"
                 INSERT INTO source_template
-                  (source_id, repo, lang, name, branch, subdir, sha256_hash, created_at)
+                  (source_id, repo, lang, name, branch, subdir, sha256_hash, created_at,
+                   description, version, authors, license, keywords, website, repository,
+                   visibility)
                 VALUES
-                  (?, ?, ?, ?, ?, ?, ?, strftime('%s','now'));
+                  (?, ?, ?, ?, ?, ?, ?, strftime('%s','now'), ?, ?, ?, ?, ?, ?, ?, ?);
                 "#,
             )
             .bind(source_id)
@@ -79,14 +95,31 @@ impl SourceMethods for LocalCache {
             .bind(&source_template_row.branch)
             .bind(&source_template_row.subdir)
             .bind(&source_template_row.sha256_hash)
+            .bind(&source_template_row.metadata.description)
+            .bind(&source_template_row.metadata.version)
+            .bind(&source_template_row.metadata.authors)
+            .bind(&source_template_row.metadata.license)
+            .bind(&source_template_row.metadata.keywords)
+            .bind(&source_template_row.metadata.website)
+            .bind(&source_template_row.metadata.repository)
+            .bind(source_template_row.visibility)
             .execute(&mut *tx)
             .await?;
 
             let source_template_id = template_result.last_insert_rowid();
+            insert_tags(
+                &mut tx,
+                &SOURCE_TEMPLATE_TAGS,
+                source_template_id,
+                &partial.tags,
+                TagOrigin::Author,
+            )
+            .await?;
 
-            let files = tmpl::list_template_files(&path).await?;
-            for file in files {
-                let content = read_file_to_string(&file)?;
+            // Paths are stored relative to the template root so the content can be written out
+            // again by `boil new <source>/<template>`. Binary files are stored without content.
+            for file_path in template_fs.list_files()? {
+                let content = template_fs.read_text(&file_path)?;
                 let _ = sqlx::query(
                     r#"
                     INSERT INTO source_template_content
@@ -96,7 +129,7 @@ impl SourceMethods for LocalCache {
                     "#,
                 )
                 .bind(source_template_id)
-                .bind(file.to_string_lossy().to_string())
+                .bind(file_path.to_string_lossy().to_string())
                 .bind(content)
                 .execute(&mut *tx)
                 .await?;
@@ -130,9 +163,69 @@ impl SourceMethods for LocalCache {
         .await?;
         Ok(results)
     }
+
+    #[tracing::instrument]
+    async fn find_source_templates(
+        &self,
+        source_name: &str,
+        template_name: &str,
+        lang: Option<String>,
+    ) -> Result<Vec<SourceTemplateResult>> {
+        let results = sqlx::query_as::<_, SourceTemplateResult>(&format!(
+            r#"
+                SELECT st.id,
+                       s.name AS source_name,
+                       st.repo,
+                       st.lang,
+                       st.name,
+                       st.branch,
+                       st.subdir,
+                       st.description,
+                       st.version,
+                       st.authors,
+                       st.license,
+                       st.keywords,
+                       st.website,
+                       st.repository,
+                       {} AS tags
+                FROM source_template AS st
+                    JOIN source AS s ON st.source_id = s.id
+                WHERE s.name = ?1
+                  AND st.name = ?2
+                  AND (?3 IS NULL OR st.lang = ?3)
+                ORDER BY st.lang
+            "#,
+            SOURCE_TEMPLATE_TAGS.select_names("st")
+        ))
+        .bind(source_name)
+        .bind(template_name)
+        .bind(lang)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results)
+    }
+
+    #[tracing::instrument]
+    async fn get_source_template_content(
+        &self,
+        source_template_id: i64,
+    ) -> Result<Vec<SourceTemplateContentRow>> {
+        let results = sqlx::query_as::<_, SourceTemplateContentRow>(
+            r#"
+                SELECT file_path, content
+                FROM source_template_content
+                WHERE source_template_id = ?
+                ORDER BY file_path
+            "#,
+        )
+        .bind(source_template_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results)
+    }
 }
 
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
 pub struct SourceRow {
     pub name: String,
     pub backend: String,
@@ -162,6 +255,10 @@ pub struct PartialSourceTemplateRow {
     pub name: String,
     pub branch: Option<String>,
     pub subdir: Option<String>,
+    pub metadata: TemplateMetadata,
+    pub visibility: Visibility,
+    // From boilermaker.toml.
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -173,6 +270,8 @@ pub struct SourceTemplateRow {
     pub branch: Option<String>,
     pub subdir: Option<String>,
     pub sha256_hash: Option<String>,
+    pub metadata: TemplateMetadata,
+    pub visibility: Visibility,
 }
 
 // TODO: increase validation
@@ -198,6 +297,8 @@ pub fn hashmap_into_source_template_row(
         branch: m.get("branch").cloned(),
         subdir: m.get("subdir").cloned(),
         sha256_hash: None,
+        metadata: TemplateMetadata::default(),
+        visibility: Visibility::infer(&url),
     };
     row = row.set_hash_string();
 
@@ -226,6 +327,26 @@ pub fn hash_source_template_row(row: &SourceTemplateRow) -> String {
     sha256_hash_string(&input)
 }
 
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SourceTemplateResult {
+    pub id: i64,
+    pub source_name: String,
+    pub repo: String,
+    pub lang: String,
+    pub name: String,
+    pub branch: Option<String>,
+    pub subdir: Option<String>,
+    // Comma-separated, sorted.
+    pub tags: Option<String>,
+}
+
+// `content` is `None` for binary files, which are only listed, not stored.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SourceTemplateContentRow {
+    pub file_path: String,
+    pub content: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct AddSourceResult {
     pub source_id: i64,
@@ -0,0 +1,269 @@
+use color_eyre::Result;
+use serde::Serialize;
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection};
+use tabled::Tabled;
+
+use super::LocalCache;
+
+#[async_trait::async_trait]
+pub trait TagMethods: Send + Sync {
+    async fn add_template_tags(
+        &self,
+        template_id: i64,
+        tags: &[String],
+        origin: TagOrigin,
+    ) -> Result<()>;
+    async fn remove_template_tags(&self, template_id: i64, tags: &[String]) -> Result<u64>;
+    async fn set_author_template_tags(&self, template_id: i64, tags: &[String]) -> Result<()>;
+    async fn add_source_template_tags(
+        &self,
+        source_template_id: i64,
+        tags: &[String],
+        origin: TagOrigin,
+    ) -> Result<()>;
+    async fn remove_source_template_tags(
+        &self,
+        source_template_id: i64,
+        tags: &[String],
+    ) -> Result<u64>;
+    async fn list_tags(&self) -> Result<Vec<TagCountRow>>;
+}
+
+#[async_trait::async_trait]
+impl TagMethods for LocalCache {
+    #[tracing::instrument]
+    async fn add_template_tags(
+        &self,
+        template_id: i64,
+        tags: &[String],
+        origin: TagOrigin,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        insert_tags(&mut tx, &TEMPLATE_TAGS, template_id, tags, origin).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[tracing::instrument]
+    async fn remove_template_tags(&self, template_id: i64, tags: &[String]) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let removed = delete_tags(&mut tx, &TEMPLATE_TAGS, template_id, tags).await?;
+        tx.commit().await?;
+        Ok(removed)
+    }
+
+    // Replace the tags from boilermaker.toml, the ones added by users stay.
+    #[tracing::instrument]
+    async fn set_author_template_tags(&self, template_id: i64, tags: &[String]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM template_tag WHERE template_id = ? AND origin = ?;")
+            .bind(template_id)
+            .bind(TagOrigin::Author)
+            .execute(&mut *tx)
+            .await?;
+        insert_tags(
+            &mut tx,
+            &TEMPLATE_TAGS,
+            template_id,
+            tags,
+            TagOrigin::Author,
+        )
+        .await?;
+        prune_tags(&mut tx).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[tracing::instrument]
+    async fn add_source_template_tags(
+        &self,
+        source_template_id: i64,
+        tags: &[String],
+        origin: TagOrigin,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        insert_tags(
+            &mut tx,
+            &SOURCE_TEMPLATE_TAGS,
+            source_template_id,
+            tags,
+            origin,
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[tracing::instrument]
+    async fn remove_source_template_tags(
+        &self,
+        source_template_id: i64,
+        tags: &[String],
+    ) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let removed = delete_tags(&mut tx, &SOURCE_TEMPLATE_TAGS, source_template_id, tags).await?;
+        tx.commit().await?;
+        Ok(removed)
+    }
+
+    // Every tag in use, with how many installed and source templates have it.
+    #[tracing::instrument]
+    async fn list_tags(&self) -> Result<Vec<TagCountRow>> {
+        let rows = sqlx::query_as::<_, TagCountRow>(
+            r#"
+            SELECT * FROM (
+                SELECT tag.name,
+                       (SELECT COUNT(*) FROM template_tag WHERE tag_id = tag.id) AS templates,
+                       (SELECT COUNT(*) FROM source_template_tag WHERE tag_id = tag.id)
+                         AS source_templates
+                FROM tag
+            )
+            WHERE templates > 0 OR source_templates > 0
+            ORDER BY name;
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, sqlx::Type)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum TagOrigin {
+    // The `tags` of boilermaker.toml.
+    Author,
+    // Added with `boil tag add`, or at install.
+    User,
+}
+
+#[derive(Debug, Clone, Serialize, Tabled, sqlx::FromRow)]
+pub struct TagCountRow {
+    pub name: String,
+    pub templates: i64,
+    pub source_templates: i64,
+}
+
+// The join table of a kind of template.
+pub(crate) struct TagTable {
+    pub(crate) table: &'static str,
+    pub(crate) fk: &'static str,
+}
+
+pub(crate) const TEMPLATE_TAGS: TagTable = TagTable {
+    table: "template_tag",
+    fk: "template_id",
+};
+
+pub(crate) const SOURCE_TEMPLATE_TAGS: TagTable = TagTable {
+    table: "source_template_tag",
+    fk: "source_template_id",
+};
+
+impl TagTable {
+    // The tags of `{alias}.id` as "a, b", to select along with the template.
+    pub(crate) fn select_names(&self, alias: &str) -> String {
+        let TagTable { table, fk } = self;
+        format!(
+            "(SELECT group_concat(name, ', ') FROM (
+                SELECT tag.name
+                FROM {table} AS tt
+                    JOIN tag ON tag.id = tt.tag_id
+                WHERE tt.{fk} = {alias}.id
+                ORDER BY tag.name
+            ))"
+        )
+    }
+}
+
+// Tags are lowercase words joined by `-`, e.g. "Web Server" becomes `web-server`. Commas separate
+// tags when they're listed, so they can't be part of one. `None` when nothing is left.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag
+        .replace(',', " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+    (!tag.is_empty()).then_some(tag)
+}
+
+pub fn normalize_tags<S: AsRef<str>>(tags: &[S]) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.iter().filter_map(|t| normalize_tag(t.as_ref())) {
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+pub(crate) async fn insert_tags(
+    conn: &mut SqliteConnection,
+    tags_table: &TagTable,
+    id: i64,
+    tags: &[String],
+    origin: TagOrigin,
+) -> Result<()> {
+    let TagTable { table, fk } = tags_table;
+    for tag in normalize_tags(tags) {
+        sqlx::query("INSERT OR IGNORE INTO tag (name) VALUES (?);")
+            .bind(&tag)
+            .execute(&mut *conn)
+            .await?;
+        // A tag the template already has keeps its origin.
+        sqlx::query(&format!(
+            "INSERT OR IGNORE INTO {table} ({fk}, tag_id, origin)
+             SELECT ?, id, ? FROM tag WHERE name = ?;"
+        ))
+        .bind(id)
+        .bind(origin)
+        .bind(&tag)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+async fn delete_tags(
+    conn: &mut SqliteConnection,
+    tags_table: &TagTable,
+    id: i64,
+    tags: &[String],
+) -> Result<u64> {
+    let tags = normalize_tags(tags);
+    if tags.is_empty() {
+        return Ok(0);
+    }
+
+    let TagTable { table, fk } = tags_table;
+    let mut qb: QueryBuilder<Sqlite> =
+        QueryBuilder::new(format!("DELETE FROM {table} WHERE {fk} = "));
+    qb.push_bind(id);
+    qb.push(" AND tag_id IN (SELECT id FROM tag WHERE name IN (");
+    let mut separated = qb.separated(", ");
+    for tag in tags {
+        separated.push_bind(tag);
+    }
+    separated.push_unseparated("))");
+    let removed = qb.build().execute(&mut *conn).await?.rows_affected();
+
+    prune_tags(conn).await?;
+    Ok(removed)
+}
+
+// Drop tags no template uses anymore.
+async fn prune_tags(conn: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(
+        r#"
+        DELETE FROM tag
+        WHERE id NOT IN (SELECT tag_id FROM template_tag)
+          AND id NOT IN (SELECT tag_id FROM source_template_tag);
+        "#,
+    )
+    .execute(conn)
+    .await?;
+    Ok(())
+}
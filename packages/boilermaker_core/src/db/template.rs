@@ -1,13 +1,20 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 use color_eyre::{Result, eyre::eyre};
-use sqlx::QueryBuilder;
+use serde::{Serialize, Serializer};
+use sqlx::{QueryBuilder, Sqlite};
 use tabled::Tabled;
+use unicode_truncate::UnicodeTruncateStr;
 
-use super::LocalCache;
+use super::{
+    LocalCache, SOURCE_TEMPLATE_TAGS, SearchQuery, TEMPLATE_TAGS, TagTable, trigram_query,
+};
+use crate::config::TemplateConfigProject;
 use crate::template as tmpl;
+use crate::template::auth::{is_private_host, url_host};
+use crate::template::fs::{DirFs, TemplateFs};
 use crate::util::crypto::sha256_hash_string;
-use crate::util::file::read_file_to_string;
 use crate::util::time::timestamp_to_iso8601;
 
 #[async_trait::async_trait]
@@ -24,28 +31,30 @@ pub trait TemplateMethods: Send + Sync {
     ) -> Result<Vec<TemplateResult>>;
     async fn template_table_exists(&self) -> Result<bool>;
     async fn update_template(&self, id: i64, row: TemplateRow) -> Result<i64>;
-    async fn search_templates(&self, term: &str) -> Result<Vec<SearchResult>>;
-    async fn search_sources(
+    async fn search_templates(&self, query: &SearchQuery) -> Result<Vec<SearchResult>>;
+    async fn search_sources(&self, query: &SearchQuery) -> Result<Vec<SearchResult>>;
+    async fn suggest_templates(&self, term: &str) -> Result<Vec<String>>;
+    async fn suggest_source_templates(
         &self,
         source_name: Option<String>,
         term: &str,
-    ) -> Result<Vec<SearchResult>>;
+    ) -> Result<Vec<String>>;
 }
 
 #[async_trait::async_trait]
 impl TemplateMethods for LocalCache {
     #[tracing::instrument]
     async fn check_unique(&self, row: &TemplateRow) -> Result<Option<TemplateResult>> {
-        let result = sqlx::query_as::<_, TemplateResult>(
+        let result = sqlx::query_as::<_, TemplateResult>(&format!(
             r#"
-            SELECT *
-            FROM template
+            {}
             WHERE
               name = ?1 AND
               lang = ?2 AND
               repo = ?3;
             "#,
-        )
+            select_templates()
+        ))
         .bind(&row.name)
         .bind(&row.lang)
         .bind(&row.repo)
@@ -60,9 +69,11 @@ impl TemplateMethods for LocalCache {
         let template_result = sqlx::query(
             r#"
             INSERT INTO template
-              (name, lang, template_dir, created_at, repo, branch, subdir, sha256_hash)
+              (name, lang, template_dir, created_at, repo, branch, subdir, sha256_hash,
+               git_ref, commit_sha, pinned, description, version, authors, license, keywords,
+               website, repository, visibility, user_description)
             VALUES
-              (?, ?, ?, strftime('%s','now'), ?, ?, ?, ?);
+              (?, ?, ?, strftime('%s','now'), ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);
             "#,
         )
         .bind(&row.name)
@@ -72,6 +83,18 @@ impl TemplateMethods for LocalCache {
         .bind(&row.branch)
         .bind(&row.subdir)
         .bind(&row.sha256_hash)
+        .bind(&row.git_ref)
+        .bind(&row.commit_sha)
+        .bind(row.pinned)
+        .bind(&row.metadata.description)
+        .bind(&row.metadata.version)
+        .bind(&row.metadata.authors)
+        .bind(&row.metadata.license)
+        .bind(&row.metadata.keywords)
+        .bind(&row.metadata.website)
+        .bind(&row.metadata.repository)
+        .bind(row.visibility)
+        .bind(&row.user_description)
         .execute(&self.pool)
         .await?;
 
@@ -91,7 +114,7 @@ impl TemplateMethods for LocalCache {
     //TODO: add regexs, fuzzy matching, predicates, etc
     #[tracing::instrument]
     async fn find_templates(&self, params: TemplateFindParams) -> Result<Vec<TemplateResult>> {
-        let mut qb = QueryBuilder::new("SELECT * FROM template WHERE 1=1");
+        let mut qb = QueryBuilder::new(format!("{} WHERE 1=1", select_templates()));
 
         /*
         if let Some(ids) = params.ids
@@ -125,6 +148,9 @@ impl TemplateMethods for LocalCache {
             qb.push(" AND subdir = ");
             qb.push_bind(subdir);
         }
+        if let Some(source) = params.source {
+            push_source_filter(&mut qb, source);
+        }
         qb.push(" ORDER BY name ASC");
 
         let q = qb.build_query_as::<TemplateResult>();
@@ -135,10 +161,11 @@ impl TemplateMethods for LocalCache {
 
     #[tracing::instrument]
     async fn get_template(&self, id: i64) -> Result<Option<TemplateResult>> {
-        let result = sqlx::query_as::<_, TemplateResult>("SELECT * FROM template WHERE id = ?;")
-            .bind(id)
-            .fetch_optional(&self.pool)
-            .await?;
+        let result =
+            sqlx::query_as::<_, TemplateResult>(&format!("{} WHERE id = ?;", select_templates()))
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?;
 
         Ok(result)
     }
@@ -150,9 +177,18 @@ impl TemplateMethods for LocalCache {
             .await?
             .ok_or_else(|| eyre!("Template with id {} not found", id))?;
 
-        let files = tmpl::list_template_files(&PathBuf::from(&t.template_dir)).await?;
-        for file in files {
-            let content = read_file_to_string(&file)?;
+        let template_dir = PathBuf::from(&t.template_dir);
+        let template_fs = DirFs::new(&template_dir);
+        let mut tx = self.pool.begin().await?;
+
+        // Reindexing replaces what was indexed before, e.g. after an update.
+        sqlx::query("DELETE FROM template_content WHERE template_id = ?;")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        for file in template_fs.list_files()? {
+            let content = template_fs.read_text(&file)?;
             let _ = sqlx::query(
                 r#"
                 INSERT INTO template_content
@@ -162,53 +198,154 @@ impl TemplateMethods for LocalCache {
                 "#,
             )
             .bind(id)
-            .bind(file.to_string_lossy().to_string())
+            .bind(template_dir.join(file).to_string_lossy().to_string())
             .bind(content)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
         }
 
+        tx.commit().await?;
         Ok(())
     }
 
-    // TODO: add options for ordering, pagination, filtering, etc
     #[tracing::instrument]
     async fn list_templates(
         &self,
-        _opts: Option<ListTemplateOptions>,
+        opts: Option<ListTemplateOptions>,
     ) -> Result<Vec<TemplateResult>> {
-        let results =
-            sqlx::query_as::<_, TemplateResult>("SELECT * FROM template ORDER BY created_at DESC;")
-                .fetch_all(&self.pool)
-                .await?;
+        let opts = opts.unwrap_or_default();
+        let mut qb = QueryBuilder::new(format!("{} WHERE 1=1", select_templates()));
+
+        if let Some(ids) = opts.ids {
+            qb.push(" AND id IN (");
+            let mut separated = qb.separated(", ");
+            for id in ids {
+                separated.push_bind(id);
+            }
+            separated.push_unseparated(")");
+        }
+        if let Some(lang) = opts.lang {
+            qb.push(" AND lang = ");
+            qb.push_bind(lang);
+        }
+        if let Some(repo) = opts.repo {
+            qb.push(" AND repo = ");
+            qb.push_bind(repo);
+        }
+        if let Some(source) = opts.source {
+            push_source_filter(&mut qb, source);
+        }
+        if let Some(installed_after) = opts.installed_after {
+            qb.push(" AND created_at >= ");
+            qb.push_bind(installed_after);
+        }
+        if let Some(visibility) = opts.visibility {
+            qb.push(" AND visibility = ");
+            qb.push_bind(visibility);
+        }
+        push_tags_filter(&mut qb, &TEMPLATE_TAGS, "template.id", &opts.tags);
+        qb.push(" ORDER BY ");
+        qb.push(opts.sort.order_by());
+        // SQLite needs a LIMIT for an OFFSET, -1 is no limit.
+        if opts.limit.is_some() || opts.offset.is_some() {
+            qb.push(" LIMIT ");
+            qb.push_bind(opts.limit.map_or(-1, |limit| limit as i64));
+            qb.push(" OFFSET ");
+            qb.push_bind(opts.offset.unwrap_or(0) as i64);
+        }
+
+        let results = qb
+            .build_query_as::<TemplateResult>()
+            .fetch_all(&self.pool)
+            .await?;
 
         Ok(results)
     }
 
+    // Match the template metadata (name, description, keywords, ...) and the file contents, ranked
+    // with bm25. Returns one result per template, best first.
     #[tracing::instrument]
-    async fn search_templates(&self, term: &str) -> Result<Vec<SearchResult>> {
-        let term = term.trim();
-        let results = sqlx::query_as::<_, SearchResult>(
-            r#"
-            SELECT 'template' as kind,
-                    src.template_id as id,
-                    t.name,
-                    t.lang,
-                    t.repo,
-                    t.branch,
-                    t.subdir
-            FROM template_content_fts AS ft_search
-                LEFT JOIN template_content AS src ON ft_search.rowid=src.id
-                LEFT JOIN template as t ON src.template_id = t.id
-            WHERE template_content_fts MATCH ?
-            GROUP BY t.id
-            "#,
-        )
-        .bind(term)
-        .fetch_all(&self.pool)
-        .await?;
+    async fn search_templates(&self, query: &SearchQuery) -> Result<Vec<SearchResult>> {
+        let mut qb = QueryBuilder::new("");
+        push_search_query(&mut qb, &INSTALLED_SEARCH, query);
+        let hits = qb
+            .build_query_as::<SearchHit>()
+            .fetch_all(&self.pool)
+            .await?;
 
-        Ok(results)
+        Ok(group_search_hits(hits))
+    }
+
+    // Installed template names close to `term`, for "did you mean" hints. Candidates share a
+    // trigram with `term`; swapped letters can break every trigram, so when none of them is close
+    // enough all names are compared.
+    #[tracing::instrument]
+    async fn suggest_templates(&self, term: &str) -> Result<Vec<String>> {
+        let select = r#"
+            SELECT t.name AS display, t.name, t.keywords
+            FROM template AS t
+        "#;
+        if let Some(trigrams) = trigram_query(term) {
+            let candidates = sqlx::query_as::<_, SuggestionCandidate>(&format!(
+                r#"{select}
+                WHERE t.id IN (SELECT rowid FROM template_trigram WHERE template_trigram MATCH ?)
+                LIMIT {MAX_CANDIDATES}"#
+            ))
+            .bind(trigrams)
+            .fetch_all(&self.pool)
+            .await?;
+            let suggestions = rank_suggestions(term, candidates);
+            if !suggestions.is_empty() {
+                return Ok(suggestions);
+            }
+        }
+
+        let candidates =
+            sqlx::query_as::<_, SuggestionCandidate>(&format!("{select} LIMIT {MAX_CANDIDATES}"))
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(rank_suggestions(term, candidates))
+    }
+
+    // Source templates (as `<source>/<template>`) with a name close to `term`, found like
+    // `suggest_templates` does.
+    #[tracing::instrument]
+    async fn suggest_source_templates(
+        &self,
+        source_name: Option<String>,
+        term: &str,
+    ) -> Result<Vec<String>> {
+        let select = r#"
+            SELECT s.name || '/' || st.name AS display, st.name, st.keywords
+            FROM source_template AS st
+                JOIN source AS s ON st.source_id = s.id
+            WHERE (?1 IS NULL OR s.name = ?1)
+        "#;
+        if let Some(trigrams) = trigram_query(term) {
+            let candidates = sqlx::query_as::<_, SuggestionCandidate>(&format!(
+                r#"{select}
+                  AND st.id IN (
+                      SELECT rowid FROM source_template_trigram
+                      WHERE source_template_trigram MATCH ?2
+                  )
+                LIMIT {MAX_CANDIDATES}"#
+            ))
+            .bind(&source_name)
+            .bind(trigrams)
+            .fetch_all(&self.pool)
+            .await?;
+            let suggestions = rank_suggestions(term, candidates);
+            if !suggestions.is_empty() {
+                return Ok(suggestions);
+            }
+        }
+
+        let candidates =
+            sqlx::query_as::<_, SuggestionCandidate>(&format!("{select} LIMIT {MAX_CANDIDATES}"))
+                .bind(&source_name)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(rank_suggestions(term, candidates))
     }
 
     #[tracing::instrument]
@@ -235,6 +372,18 @@ impl TemplateMethods for LocalCache {
                 branch = ?,
                 subdir = ?,
                 sha256_hash = ?,
+                git_ref = ?,
+                commit_sha = ?,
+                pinned = ?,
+                description = ?,
+                version = ?,
+                authors = ?,
+                license = ?,
+                keywords = ?,
+                website = ?,
+                repository = ?,
+                visibility = ?,
+                user_description = ?,
                 updated_at = unixepoch()
             WHERE id = ?
             RETURNING id;
@@ -247,6 +396,18 @@ impl TemplateMethods for LocalCache {
         .bind(row.branch)
         .bind(row.subdir)
         .bind(row.sha256_hash)
+        .bind(row.git_ref)
+        .bind(row.commit_sha)
+        .bind(row.pinned)
+        .bind(row.metadata.description)
+        .bind(row.metadata.version)
+        .bind(row.metadata.authors)
+        .bind(row.metadata.license)
+        .bind(row.metadata.keywords)
+        .bind(row.metadata.website)
+        .bind(row.metadata.repository)
+        .bind(row.visibility)
+        .bind(row.user_description)
         .bind(id)
         .execute(&self.pool)
         .await?;
@@ -254,39 +415,18 @@ impl TemplateMethods for LocalCache {
         Ok(id)
     }
 
-    // Search the content of all templates in source_name.
-    async fn search_sources(
-        &self,
-        source_name: Option<String>,
-        term: &str,
-    ) -> Result<Vec<SearchResult>> {
-        let term = term.trim();
-        let mut qb = QueryBuilder::new(
-            r#"
-                SELECT 'source' as kind,
-                       st.id,
-                       st.name,
-                       st.lang,
-                       st.repo,
-                       st.branch,
-                       st.subdir
-                FROM source_template_content_fts AS ft_search
-                    LEFT JOIN source_template_content AS stc ON ft_search.rowid = stc.id
-                    LEFT JOIN source_template as st ON stc.source_template_id = st.id
-                    LEFT JOIN source as s ON st.source_id = s.id
-                WHERE source_template_content_fts MATCH
-            "#,
-        );
-        qb.push_bind(term);
-
-        if let Some(name) = source_name {
-            qb.push(" AND s.name = ");
-            qb.push_bind(name);
-        }
-        qb.push(" GROUP BY st.id");
+    // Search the metadata and content of the templates of all sources, ranked like
+    // `search_templates`.
+    #[tracing::instrument]
+    async fn search_sources(&self, query: &SearchQuery) -> Result<Vec<SearchResult>> {
+        let mut qb = QueryBuilder::new("");
+        push_search_query(&mut qb, &SOURCE_SEARCH, query);
+        let hits = qb
+            .build_query_as::<SearchHit>()
+            .fetch_all(&self.pool)
+            .await?;
 
-        let q = qb.build_query_as::<SearchResult>();
-        Ok(q.fetch_all(&self.pool).await?)
+        Ok(group_search_hits(hits))
     }
 }
 
@@ -299,6 +439,13 @@ pub struct TemplateRow {
     pub branch: Option<String>,
     pub subdir: Option<String>,
     pub sha256_hash: Option<String>,
+    pub git_ref: Option<String>,
+    pub commit_sha: Option<String>,
+    pub pinned: bool,
+    pub metadata: TemplateMetadata,
+    pub visibility: Visibility,
+    // Set by the user at install, shown instead of the description of boilermaker.toml.
+    pub user_description: Option<String>,
 }
 
 impl TemplateRow {
@@ -320,11 +467,17 @@ impl From<TemplateResult> for TemplateRow {
             branch: value.branch,
             subdir: value.subdir,
             sha256_hash: value.sha256_hash,
+            git_ref: value.git_ref,
+            commit_sha: value.commit_sha,
+            pinned: value.pinned,
+            metadata: value.metadata,
+            visibility: value.visibility,
+            user_description: value.user_description,
         }
     }
 }
 
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
 pub struct TemplateResult {
     pub id: i64,
     pub name: String,
@@ -336,6 +489,97 @@ pub struct TemplateResult {
     pub sha256_hash: Option<String>,
     pub created_at: Option<i32>,
     pub updated_at: Option<i32>,
+    pub git_ref: Option<String>,
+    pub commit_sha: Option<String>,
+    pub pinned: bool,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub metadata: TemplateMetadata,
+    pub visibility: Visibility,
+    pub user_description: Option<String>,
+    // Comma-separated, sorted.
+    pub tags: Option<String>,
+}
+
+impl TemplateResult {
+    // The user's description, or the one of boilermaker.toml.
+    pub fn description(&self) -> Option<&str> {
+        self.user_description
+            .as_deref()
+            .or(self.metadata.description.as_deref())
+    }
+}
+
+// Private templates come from local paths or hosts marked private in the config, or were installed
+// with `--visibility private`. They're left out of `--public` listings and exports.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, sqlx::Type, clap::ValueEnum)]
+#[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    #[default]
+    Public,
+    Private,
+}
+
+impl Visibility {
+    #[tracing::instrument]
+    pub fn infer(repo: &str) -> Self {
+        match url_host(repo) {
+            Some(host) if !is_private_host(&host) => Visibility::Public,
+            _ => Visibility::Private,
+        }
+    }
+
+    // The filter of `--public`/`--private` flags, `None` for everything.
+    pub fn from_flags(public: bool, private: bool) -> Option<Self> {
+        match (public, private) {
+            (true, false) => Some(Visibility::Public),
+            (false, true) => Some(Visibility::Private),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Visibility {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Visibility::Public => write!(f, "public"),
+            Visibility::Private => write!(f, "private"),
+        }
+    }
+}
+
+// The `[project]` table of a template's boilermaker.toml. Lists are stored comma-separated so they
+// can be searched like the other columns.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, sqlx::FromRow)]
+pub struct TemplateMetadata {
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub authors: Option<String>,
+    pub license: Option<String>,
+    pub keywords: Option<String>,
+    pub website: Option<String>,
+    pub repository: Option<String>,
+}
+
+impl From<&TemplateConfigProject> for TemplateMetadata {
+    fn from(project: &TemplateConfigProject) -> Self {
+        let list = |items: &Option<Vec<String>>| {
+            items
+                .as_ref()
+                .filter(|items| !items.is_empty())
+                .map(|items| items.join(", "))
+        };
+        Self {
+            description: project.description.clone(),
+            version: project.version.clone(),
+            authors: list(&project.authors),
+            license: project.license.clone(),
+            keywords: list(&project.keywords),
+            website: project.website.clone(),
+            repository: project.repository.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -347,13 +591,94 @@ pub struct TemplateFindParams {
     pub branch: Option<String>,
     pub subdir: Option<String>,
     pub sha256_hash: Option<String>,
+    pub source: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ListTemplateOptions {
-    pub order_by: Option<String>,
+    // Only these templates, e.g. the hits of a search.
+    pub ids: Option<Vec<i64>>,
+    pub sort: TemplateSort,
     pub limit: Option<u64>,
     pub offset: Option<u64>,
+    pub lang: Option<String>,
+    pub repo: Option<String>,
+    pub source: Option<String>,
+    // Seconds since the epoch.
+    pub installed_after: Option<i64>,
+    pub visibility: Option<Visibility>,
+    // Templates with all of these tags.
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TemplateSort {
+    // Most recently installed first.
+    #[default]
+    Newest,
+    Oldest,
+    Name,
+    Lang,
+    // Most recently updated first, never updated last.
+    Updated,
+}
+
+impl TemplateSort {
+    // Every order ends on `id`, so pages don't overlap.
+    fn order_by(self) -> &'static str {
+        match self {
+            TemplateSort::Newest => "created_at DESC, id DESC",
+            TemplateSort::Oldest => "created_at ASC, id ASC",
+            TemplateSort::Name => "name ASC, lang ASC, id ASC",
+            TemplateSort::Lang => "lang ASC, name ASC, id ASC",
+            TemplateSort::Updated => "updated_at IS NULL, updated_at DESC, id DESC",
+        }
+    }
+}
+
+// Installed templates have no FK to a source, so match them on repo + subdir instead.
+fn push_source_filter(qb: &mut QueryBuilder<'_, Sqlite>, source: String) {
+    qb.push(
+        r#" AND EXISTS (
+            SELECT 1
+            FROM source_template AS st
+                JOIN source AS s ON st.source_id = s.id
+            WHERE st.repo = template.repo
+              AND IFNULL(st.subdir, '') = IFNULL(template.subdir, '')
+              AND s.name = "#,
+    );
+    qb.push_bind(source);
+    qb.push(")");
+}
+
+// Installed templates along with their tags.
+fn select_templates() -> String {
+    format!(
+        "SELECT template.*, {} AS tags FROM template",
+        TEMPLATE_TAGS.select_names("template")
+    )
+}
+
+// Keep the rows (`id` is their id column) that have every one of `tags`.
+fn push_tags_filter(
+    qb: &mut QueryBuilder<'_, Sqlite>,
+    tags_table: &TagTable,
+    id: &str,
+    tags: &[String],
+) {
+    let TagTable { table, fk } = tags_table;
+    for tag in tags {
+        qb.push(format!(
+            r#" AND EXISTS (
+                SELECT 1
+                FROM {table} AS tt
+                    JOIN tag ON tag.id = tt.tag_id
+                WHERE tt.{fk} = {id}
+                  AND tag.name = "#
+        ));
+        qb.push_bind(tag.to_owned());
+        qb.push(")");
+    }
 }
 
 #[derive(Debug, Tabled)]
@@ -361,18 +686,41 @@ pub struct TabledTemplateListResult {
     pub id: i64,
     pub name: String,
     pub lang: String,
+    pub version: String,
+    pub description: String,
+    pub keywords: String,
+    pub tags: String,
+    pub visibility: String,
     pub repo: String,
+    #[tabled(rename = "ref")]
+    pub git_ref: String,
+    pub commit: String,
     pub created_at: String,
     pub updated_at: String,
 }
 
 impl TabledTemplateListResult {
     pub fn from(row: TemplateResult) -> Self {
+        let description = truncate_description(row.description().map(str::to_owned));
         Self {
             id: row.id,
             name: row.name,
             lang: row.lang,
+            version: row.metadata.version.unwrap_or_else(|| "-".to_string()),
+            description,
+            keywords: row.metadata.keywords.unwrap_or_else(|| "-".to_string()),
+            tags: row.tags.unwrap_or_else(|| "-".to_string()),
+            visibility: row.visibility.to_string(),
             repo: row.repo,
+            git_ref: match (row.git_ref, row.pinned) {
+                (Some(git_ref), true) => format!("{git_ref} 📌"),
+                (Some(git_ref), false) => git_ref,
+                (None, _) => "-".to_string(),
+            },
+            commit: row
+                .commit_sha
+                .map(|sha| tmpl::short_sha(&sha))
+                .unwrap_or_else(|| "-".to_string()),
             created_at: row
                 .created_at
                 .map(|v| timestamp_to_iso8601(v as i64))
@@ -385,6 +733,22 @@ impl TabledTemplateListResult {
     }
 }
 
+// Descriptions can be long, keep tables readable.
+fn truncate_description(description: Option<String>) -> String {
+    match description {
+        Some(d) if d.is_empty() => "-".to_string(),
+        Some(d) => {
+            let (cut, _) = d.unicode_truncate(39);
+            if cut.len() < d.len() {
+                format!("{}…", cut.trim_end())
+            } else {
+                d
+            }
+        }
+        None => "-".to_string(),
+    }
+}
+
 pub fn hash_template_row(row: &TemplateRow) -> String {
     let input = format!(
         "{}~~{}~~{}~~{}~~{}",
@@ -397,8 +761,9 @@ pub fn hash_template_row(row: &TemplateRow) -> String {
     sha256_hash_string(&input)
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, sqlx::Type)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, sqlx::Type)]
 #[sqlx(type_name = "TEXT", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
 pub enum SearchResultKind {
     Template,
     Source,
@@ -413,7 +778,7 @@ impl std::fmt::Display for SearchResultKind {
     }
 }
 
-#[derive(Debug, Clone, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
 pub struct SearchResult {
     pub kind: SearchResultKind,
     pub id: i64,
@@ -422,19 +787,353 @@ pub struct SearchResult {
     pub repo: String,
     pub branch: Option<String>,
     pub subdir: Option<String>,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub keywords: Option<String>,
+    pub tags: Option<String>,
+    pub visibility: Visibility,
+    // Higher is better.
+    pub score: f64,
+    #[sqlx(skip)]
+    pub matches: Vec<SearchMatch>,
+}
+
+// A match in the metadata (no `file_path`) or in one of the files of a template. Snippets mark the
+// matched terms with `SNIPPET_START`/`SNIPPET_END`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatch {
+    pub file_path: Option<String>,
+    #[serde(serialize_with = "serialize_snippet")]
+    pub snippet: Option<String>,
+}
+
+// Serialized snippets are plain text, the markers are only meant for highlighting.
+fn serialize_snippet<S: Serializer>(
+    snippet: &Option<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    snippet
+        .as_ref()
+        .map(|s| s.replace([SNIPPET_START, SNIPPET_END], ""))
+        .serialize(serializer)
+}
+
+pub const SNIPPET_START: char = '\u{2}';
+pub const SNIPPET_END: char = '\u{3}';
+
+// The columns of template_fts and source_template_fts worth searching: not the install dir or the
+// row hash. File contents are matched on `content` only, installed files have absolute paths.
+const SEARCH_FTS_COLUMNS: &str = "{name lang repo branch subdir description version authors license keywords website repository}";
+
+// Installed and source templates are searched the same way, through these tables. In the queries
+// `t` is the template table and `c` the content table.
+struct SearchTables {
+    kind: &'static str,
+    template: &'static str,
+    template_fts: &'static str,
+    // bm25 weights for the template_fts columns, name hits count the most.
+    template_fts_weights: &'static str,
+    content: &'static str,
+    content_fts: &'static str,
+    content_fk: &'static str,
+    // The path of a content row relative to the template root.
+    path: &'static str,
+    // Matches `hits` rows of templates in a source, the source names are pushed after it.
+    in_source: &'static str,
+    tags: TagTable,
+}
+
+const INSTALLED_SEARCH: SearchTables = SearchTables {
+    kind: "template",
+    template: "template",
+    template_fts: "template_fts",
+    template_fts_weights: "10.0, 2.0, 0.0, 1.0, 0.5, 0.5, 0.0, 3.0, 0.5, 1.0, 0.5, 4.0, 0.5, 1.0",
+    content: "template_content",
+    content_fts: "template_content_fts",
+    content_fk: "template_id",
+    path: "substr(c.file_path, length(t.template_dir) + 2)",
+    // Installed templates have no FK to a source, so match them on repo + subdir instead.
+    in_source: r#"EXISTS (
+        SELECT 1
+        FROM source_template AS st
+            JOIN source AS s ON st.source_id = s.id
+        WHERE st.repo = hits.repo
+          AND IFNULL(st.subdir, '') = IFNULL(hits.subdir, '')
+          AND s.name IN "#,
+    tags: TEMPLATE_TAGS,
+};
+
+const SOURCE_SEARCH: SearchTables = SearchTables {
+    kind: "source",
+    template: "source_template",
+    template_fts: "source_template_fts",
+    template_fts_weights: "1.0, 2.0, 10.0, 0.5, 0.5, 0.0, 3.0, 0.5, 1.0, 0.5, 4.0, 0.5, 1.0",
+    content: "source_template_content",
+    content_fts: "source_template_content_fts",
+    content_fk: "source_template_id",
+    path: "c.file_path",
+    in_source: r#"EXISTS (
+        SELECT 1
+        FROM source_template AS st
+            JOIN source AS s ON st.source_id = s.id
+        WHERE st.id = hits.id
+          AND s.name IN "#,
+    tags: SOURCE_TEMPLATE_TAGS,
+};
+
+// Build the search as a union of hits: metadata and content matches for free text, or every
+// template (or file, with `path:`) when the search only has filters. The filters apply to all of
+// them.
+fn push_search_query(
+    qb: &mut QueryBuilder<'_, Sqlite>,
+    tables: &SearchTables,
+    query: &SearchQuery,
+) {
+    let SearchTables {
+        kind,
+        template,
+        template_fts,
+        template_fts_weights,
+        content,
+        content_fts,
+        content_fk,
+        path,
+        in_source,
+        tags,
+    } = tables;
+    let columns = format!(
+        "'{kind}' AS kind, t.id, t.name, t.lang, t.repo, t.branch, t.subdir, t.description, \
+         t.version, t.keywords, {} AS tags, t.visibility",
+        tags.select_names("t")
+    );
+
+    qb.push("SELECT * FROM (");
+    match &query.fts {
+        Some(fts) => {
+            qb.push(format!(
+                r#"
+                SELECT {columns},
+                       bm25({template_fts}, {template_fts_weights}) AS score,
+                       NULL AS file_path,
+                       snippet({template_fts}, -1, char(2), char(3), '…', 12) AS snippet
+                FROM {template_fts}
+                    JOIN {template} AS t ON t.id = {template_fts}.rowid
+                WHERE {template_fts} MATCH "#
+            ));
+            qb.push_bind(format!("{SEARCH_FTS_COLUMNS} : ({fts})"));
+            qb.push(format!(
+                r#"
+                UNION ALL
+                SELECT {columns},
+                       bm25({content_fts}, 0.0, 1.0) AS score,
+                       {path} AS file_path,
+                       snippet({content_fts}, 1, char(2), char(3), '…', 12) AS snippet
+                FROM {content_fts}
+                    JOIN {content} AS c ON c.id = {content_fts}.rowid
+                    JOIN {template} AS t ON t.id = c.{content_fk}
+                WHERE {content_fts} MATCH "#
+            ));
+            qb.push_bind(format!("{{content}} : ({fts})"));
+        }
+        None if !query.paths.is_empty() => {
+            qb.push(format!(
+                r#"
+                SELECT {columns}, 0.0 AS score, {path} AS file_path, NULL AS snippet
+                FROM {content} AS c
+                    JOIN {template} AS t ON t.id = c.{content_fk}"#
+            ));
+        }
+        None => {
+            qb.push(format!(
+                r#"
+                SELECT {columns}, 0.0 AS score, NULL AS file_path, NULL AS snippet
+                FROM {template} AS t"#
+            ));
+        }
+    }
+    qb.push(") AS hits WHERE 1=1");
+
+    if !query.langs.is_empty() {
+        qb.push(" AND lower(hits.lang) IN ");
+        push_bind_list(qb, &query.langs);
+    }
+    if !query.names.is_empty() {
+        qb.push(" AND (0");
+        for name in &query.names {
+            qb.push(r" OR hits.name LIKE ");
+            qb.push_bind(like_pattern(name));
+            qb.push(r" ESCAPE '\'");
+        }
+        qb.push(")");
+    }
+    if !query.paths.is_empty() {
+        // Only the matching files are listed, metadata hits count when the template has one.
+        qb.push(format!(
+            r#" AND EXISTS (
+                SELECT 1
+                FROM {content} AS c
+                    JOIN {template} AS t ON t.id = c.{content_fk}
+                WHERE t.id = hits.id AND (0"#
+        ));
+        for p in &query.paths {
+            qb.push(format!(" OR {path} LIKE "));
+            qb.push_bind(like_pattern(p));
+            qb.push(r" ESCAPE '\'");
+        }
+        qb.push("))");
+        qb.push(" AND (hits.file_path IS NULL OR 0");
+        for p in &query.paths {
+            qb.push(" OR hits.file_path LIKE ");
+            qb.push_bind(like_pattern(p));
+            qb.push(r" ESCAPE '\'");
+        }
+        qb.push(")");
+    }
+    push_tags_filter(qb, tags, "hits.id", &query.tags);
+    if let Some(visibility) = query.visibility {
+        qb.push(" AND hits.visibility = ");
+        qb.push_bind(visibility);
+    }
+    if !query.sources.is_empty() {
+        qb.push(" AND ");
+        qb.push(*in_source);
+        push_bind_list(qb, &query.sources);
+        qb.push(")");
+    }
+    qb.push(" ORDER BY score, hits.name, hits.lang");
+}
+
+fn push_bind_list(qb: &mut QueryBuilder<'_, Sqlite>, values: &[String]) {
+    qb.push("(");
+    let mut separated = qb.separated(", ");
+    for value in values {
+        separated.push_bind(value.to_owned());
+    }
+    separated.push_unseparated(")");
+}
+
+// A case-insensitive substring match for LIKE, with `*` as a wildcard.
+fn like_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', r"\\")
+        .replace('%', r"\%")
+        .replace('_', r"\_")
+        .replace('*', "%");
+    format!("%{escaped}%")
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct SearchHit {
+    #[sqlx(flatten)]
+    result: SearchResult,
+    file_path: Option<String>,
+    snippet: Option<String>,
+}
+
+// Each extra matching file (or the metadata) adds 10% of the best hit's score, for up to 5 of them,
+// so a template with many files can't outrank a better match on volume alone.
+const EXTRA_MATCH_BONUS: f64 = 0.1;
+const MAX_BONUS_MATCHES: usize = 5;
+
+// Group the hits (sorted best first) into one result per template. bm25 scores are negative and
+// lower is better, so a template scores its best hit negated, plus a small bonus for the rest.
+// Files of the template's other langs are left out, since every lang of a template shares one dir.
+fn group_search_hits(hits: Vec<SearchHit>) -> Vec<SearchResult> {
+    let mut results: Vec<SearchResult> = Vec::new();
+    let mut positions: HashMap<(String, i64), usize> = HashMap::new();
+    for hit in hits {
+        let lang_prefix = format!("{}/", hit.result.lang);
+        if let Some(path) = &hit.file_path
+            && path.contains('/')
+            && !path.starts_with(&lang_prefix)
+        {
+            continue;
+        }
+
+        let key = (hit.result.kind.to_string(), hit.result.id);
+        let pos = match positions.get(&key) {
+            Some(pos) => *pos,
+            None => {
+                results.push(SearchResult {
+                    score: -hit.result.score,
+                    matches: Vec::new(),
+                    ..hit.result.clone()
+                });
+                positions.insert(key, results.len() - 1);
+                results.len() - 1
+            }
+        };
+        results[pos].matches.push(SearchMatch {
+            file_path: hit.file_path,
+            snippet: hit.snippet,
+        });
+    }
+    for result in &mut results {
+        let extra = result
+            .matches
+            .len()
+            .saturating_sub(1)
+            .min(MAX_BONUS_MATCHES);
+        result.score *= 1.0 + EXTRA_MATCH_BONUS * extra as f64;
+    }
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results
+}
+
+const MAX_SUGGESTIONS: usize = 5;
+const MAX_CANDIDATES: usize = 500;
+const MIN_SIMILARITY: f64 = 0.6;
+
+#[derive(Debug, sqlx::FromRow)]
+struct SuggestionCandidate {
+    display: String,
+    name: String,
+    keywords: Option<String>,
+}
+
+// The candidates whose name or one of its keywords is close enough to `term`, most similar first.
+// Damerau-Levenshtein counts swapped letters (`fastpai`) as a single edit.
+fn rank_suggestions(term: &str, candidates: Vec<SuggestionCandidate>) -> Vec<String> {
+    let term = term.trim().to_lowercase();
+    let mut scored: Vec<(f64, String)> = candidates
+        .into_iter()
+        .filter_map(|c| {
+            let keywords = c.keywords.as_deref().unwrap_or("");
+            let score = std::iter::once(c.name.as_str())
+                .chain(keywords.split(',').map(str::trim))
+                .filter(|word| !word.is_empty())
+                .map(|word| strsim::normalized_damerau_levenshtein(&term, &word.to_lowercase()))
+                .fold(0.0, f64::max);
+            (score >= MIN_SIMILARITY).then_some((score, c.display))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+
+    let mut suggestions: Vec<String> = Vec::new();
+    for (_, display) in scored {
+        if !suggestions.contains(&display) {
+            suggestions.push(display);
+        }
+    }
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
 }
 
 #[derive(Debug, Tabled)]
 pub struct TabledSearchResult {
-    #[tabled(skip)]
-    pub kind: SearchResultKind,
     #[tabled(skip)]
     pub id: i64,
+    pub kind: SearchResultKind,
     pub name: String,
     pub lang: String,
+    pub version: String,
+    pub description: String,
+    pub keywords: String,
+    pub tags: String,
     pub repo: String,
     pub branch: String,
     pub subdir: String,
+    pub files: usize,
 }
 
 impl TabledSearchResult {
@@ -444,9 +1143,14 @@ impl TabledSearchResult {
             id: sr.id,
             name: sr.name,
             lang: sr.lang,
+            version: sr.version.unwrap_or_else(|| "-".to_owned()),
+            description: truncate_description(sr.description),
+            keywords: sr.keywords.unwrap_or_else(|| "-".to_owned()),
+            tags: sr.tags.unwrap_or_else(|| "-".to_owned()),
             repo: sr.repo,
             branch: sr.branch.unwrap_or_else(|| "-".to_owned()),
             subdir: sr.subdir.unwrap_or_else(|| "-".to_owned()),
+            files: sr.matches.iter().filter(|m| m.file_path.is_some()).count(),
         }
     }
 }
@@ -46,10 +46,16 @@ where
 #[tracing::instrument]
 pub fn init_tracing(debug_level: u8) -> Result<()> {
     //TODO: Add more specific formatting for each debug level (0-4)
+    // Logs go to stderr, stdout is kept for command output (see `--format`).
     let fmt_layer: Box<dyn tracing_subscriber::Layer<_> + Send + Sync> = match debug_level {
-        0 => Box::new(fmt::layer().event_format(DefaultFormatter)),
+        0 => Box::new(
+            fmt::layer()
+                .with_writer(std::io::stderr)
+                .event_format(DefaultFormatter),
+        ),
         1 => Box::new(
             fmt::layer()
+                .with_writer(std::io::stderr)
                 .event_format(fmt::format().compact())
                 .with_thread_ids(true)
                 .with_thread_names(true)
@@ -58,6 +64,7 @@ pub fn init_tracing(debug_level: u8) -> Result<()> {
         ),
         2.. => Box::new(
             fmt::layer()
+                .with_writer(std::io::stderr)
                 .event_format(fmt::format().pretty())
                 .with_thread_ids(true)
                 .with_thread_names(true)
@@ -3,6 +3,7 @@ use std::sync::Arc;
 
 use crate::config::SysConfig;
 use crate::db::TemplateDb;
+use crate::util::output::OutputFormat;
 
 pub type TemplateDbType = Arc<dyn TemplateDb + Send + Sync>;
 
@@ -10,6 +11,7 @@ pub struct AppState {
     pub log_level: u8,
     pub sys_config: SysConfig,
     pub local_db: TemplateDbType,
+    pub output_format: OutputFormat,
 }
 
 impl fmt::Debug for AppState {
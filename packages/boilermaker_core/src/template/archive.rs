@@ -0,0 +1,354 @@
+use std::fs::{self, File};
+use std::io::{Cursor, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use color_eyre::{Result, eyre::eyre};
+use flate2::read::GzDecoder;
+use git2::Repository;
+use tracing::{debug, info};
+
+use super::fs::{DirFs, GitTreeFs, MemoryFs, TemplateFs, safe_relative_path};
+use super::pack::{PACK_TEMPLATE_DIR, PackManifest, read_pack_manifest};
+use super::{CloneContext, ClonedRepo, fetch_repo, is_local_url, short_sha};
+
+// Where a template comes from. Anything that isn't a local directory or an archive is treated as
+// a git repo, which keeps `boil install <url>` working as before.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateLocation {
+    Git(String),
+    // A plain directory. Local git repos are installed through git so they can be pinned/updated.
+    LocalDir(PathBuf),
+    Archive(PathBuf),
+    RemoteArchive(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    TarGz,
+    Zip,
+}
+
+impl ArchiveKind {
+    #[tracing::instrument]
+    pub fn from_name(name: &str) -> Option<Self> {
+        // Ignore the query string of URLs, e.g. signed download links.
+        let name = name.split(['?', '#']).next().unwrap_or(name).to_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+impl TemplateLocation {
+    #[tracing::instrument]
+    pub fn detect(location: &str) -> Self {
+        let is_archive = ArchiveKind::from_name(location).is_some();
+
+        if location.starts_with("http://") || location.starts_with("https://") {
+            return if is_archive {
+                TemplateLocation::RemoteArchive(location.to_owned())
+            } else {
+                TemplateLocation::Git(location.to_owned())
+            };
+        }
+
+        if is_local_url(location) {
+            let path = PathBuf::from(location.strip_prefix("file://").unwrap_or(location));
+            if is_archive && path.is_file() {
+                return TemplateLocation::Archive(path);
+            }
+            if path.is_dir() && Repository::open(&path).is_err() {
+                return TemplateLocation::LocalDir(path);
+            }
+            // Local repos are kept by absolute path, so `./x` means the same repo from any dir.
+            if let Ok(path) = fs::canonicalize(&path) {
+                return TemplateLocation::Git(path.to_string_lossy().to_string());
+            }
+        }
+
+        TemplateLocation::Git(location.to_owned())
+    }
+
+    pub fn is_git(&self) -> bool {
+        matches!(self, TemplateLocation::Git(_))
+    }
+
+    // What to store as the template's repo. Local paths (repos included, see `detect`) are made
+    // absolute so `boil update` finds them again from any working directory.
+    #[tracing::instrument]
+    pub fn to_repo_string(&self) -> Result<String> {
+        match self {
+            TemplateLocation::Git(url) | TemplateLocation::RemoteArchive(url) => Ok(url.to_owned()),
+            TemplateLocation::LocalDir(path) | TemplateLocation::Archive(path) => {
+                Ok(fs::canonicalize(path)?.to_string_lossy().to_string())
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct OpenedTemplate {
+    pub fs: Box<dyn TemplateFs>,
+    // Set for git locations.
+    pub cloned: Option<ClonedRepo>,
+    // Set for archives made by `boil template pack`.
+    pub manifest: Option<PackManifest>,
+}
+
+impl OpenedTemplate {
+    // The commit the files came from: the fetched one, or the one a pack was made from.
+    pub fn commit_sha(&self) -> Option<String> {
+        match (&self.cloned, &self.manifest) {
+            (Some(cloned), _) => Some(cloned.commit_sha.to_owned()),
+            (None, Some(manifest)) => manifest.commit.clone(),
+            (None, None) => None,
+        }
+    }
+}
+
+// Open the template files of any location, limited to `ctx.subdir` when set. Git repos are read
+// from the mirror and archives from memory, so nothing is written to disk.
+#[tracing::instrument]
+pub async fn open_template(
+    location: &TemplateLocation,
+    ctx: &CloneContext,
+) -> Result<OpenedTemplate> {
+    if !location.is_git() && (ctx.git_ref.is_some() || ctx.branch.is_some()) {
+        return Err(eyre!("💥 Refs and branches only apply to git repos."));
+    }
+    let subdir = ctx
+        .subdir
+        .as_deref()
+        .map(|s| s.trim_matches('/'))
+        .filter(|s| !s.is_empty());
+    // Subdirs come from the command line or a source, they must not lead out of the template.
+    if let Some(subdir) = subdir {
+        safe_relative_path(Path::new(subdir))?;
+    }
+
+    let archive = match location {
+        TemplateLocation::Git(_) => {
+            info!("Fetching template");
+            let cloned = fetch_repo(ctx).await?;
+            info!(
+                "Resolved {} `{}` to commit {}",
+                cloned.ref_kind,
+                cloned.git_ref,
+                short_sha(&cloned.commit_sha)
+            );
+            let fs = GitTreeFs::open(cloned.repo.path(), &cloned.commit_sha, subdir)?;
+            return Ok(OpenedTemplate {
+                fs: Box::new(fs),
+                cloned: Some(cloned),
+                manifest: None,
+            });
+        }
+        TemplateLocation::LocalDir(path) => {
+            info!("Reading template from {}", path.display());
+            let root = match subdir {
+                Some(subdir) => path.join(subdir),
+                None => path.to_path_buf(),
+            };
+            return Ok(OpenedTemplate {
+                fs: Box::new(DirFs::new(root)),
+                cloned: None,
+                manifest: None,
+            });
+        }
+        TemplateLocation::Archive(path) => {
+            info!("Reading template archive {}", path.display());
+            let kind = ArchiveKind::from_name(&path.to_string_lossy())
+                .ok_or_else(|| eyre!("💥 Unsupported archive type: {}", path.display()))?;
+            read_archive(File::open(path)?, kind)?
+        }
+        TemplateLocation::RemoteArchive(url) => {
+            info!("Downloading template archive {url}");
+            let kind = ArchiveKind::from_name(url)
+                .ok_or_else(|| eyre!("💥 Unsupported archive type: {url}"))?;
+            let bytes = reqwest::get(url).await?.error_for_status()?.bytes().await?;
+            read_archive(Cursor::new(bytes), kind)?
+        }
+    };
+
+    let (root, manifest) = archive_root(archive)?;
+    let root = match subdir {
+        Some(subdir) => root.subdir(Path::new(subdir)),
+        None => root,
+    };
+    Ok(OpenedTemplate {
+        fs: Box::new(root),
+        cloned: None,
+        manifest,
+    })
+}
+
+// The template root of an archive: the `template/` dir of packs, or the single top-level
+// directory of archives that wrap everything in one (like GitHub's source downloads do).
+fn archive_root(archive: MemoryFs) -> Result<(MemoryFs, Option<PackManifest>)> {
+    if let Some(manifest) = read_pack_manifest(&archive)? {
+        return Ok((archive.subdir(Path::new(PACK_TEMPLATE_DIR)), Some(manifest)));
+    }
+
+    if archive.exists(Path::new("boilermaker.toml")) {
+        return Ok((archive, None));
+    }
+    match archive.single_top_level_dir() {
+        Some(top) => Ok((archive.subdir(&top), None)),
+        None => Ok((archive, None)),
+    }
+}
+
+// Read the files of a .tar.gz or .zip archive into memory. Entries with absolute paths or `..`
+// components are refused, symlinks and other special entries are skipped.
+#[tracing::instrument(skip(reader))]
+pub fn read_archive<R: Read + Seek>(reader: R, kind: ArchiveKind) -> Result<MemoryFs> {
+    match kind {
+        ArchiveKind::TarGz => read_tar_gz(reader),
+        ArchiveKind::Zip => read_zip(reader),
+    }
+}
+
+fn read_tar_gz<R: Read>(reader: R) -> Result<MemoryFs> {
+    let mut files = MemoryFs::new();
+    let mut archive = tar::Archive::new(GzDecoder::new(reader));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let entry_path = entry.path()?.into_owned();
+        let rel = safe_relative_path(&entry_path)?;
+        match entry.header().entry_type() {
+            tar::EntryType::Regular | tar::EntryType::Continuous => {
+                let executable = entry.header().mode().is_ok_and(|m| m & 0o111 != 0);
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data)?;
+                files.insert(&rel, data, executable)?;
+            }
+            tar::EntryType::Directory => {}
+            other => debug!("Skipping {:?} archive entry: {}", other, rel.display()),
+        }
+    }
+    Ok(files)
+}
+
+fn read_zip<R: Read + Seek>(reader: R) -> Result<MemoryFs> {
+    let mut files = MemoryFs::new();
+    let mut archive = zip::ZipArchive::new(reader)?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        let rel = safe_relative_path(Path::new(file.name()))?;
+        if file.is_dir() {
+            continue;
+        }
+        if file.is_symlink() {
+            debug!("Skipping symlink archive entry: {}", rel.display());
+            continue;
+        }
+        let executable = file.unix_mode().is_some_and(|m| m & 0o111 != 0);
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        files.insert(&rel, data, executable)?;
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use flate2::{Compression, write::GzEncoder};
+
+    use super::*;
+
+    // (path, entry type, data). Paths are written as is, `tar::Builder` would refuse the bad ones.
+    fn tar_gz(entries: &[(&str, tar::EntryType, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, entry_type, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.as_gnu_mut().unwrap().name[..path.len()].copy_from_slice(path.as_bytes());
+            header.set_entry_type(*entry_type);
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            if *entry_type == tar::EntryType::Symlink {
+                header.set_link_name("/etc/passwd").unwrap();
+            }
+            header.set_cksum();
+            builder.append(&header, *data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    fn zip(files: &[&str], symlinks: &[&str]) -> Vec<u8> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        for path in files {
+            zip.start_file(*path, options).unwrap();
+            zip.write_all(b"data").unwrap();
+        }
+        for path in symlinks {
+            zip.add_symlink(*path, "/etc/passwd", options).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn read(bytes: Vec<u8>, kind: ArchiveKind) -> Result<MemoryFs> {
+        read_archive(Cursor::new(bytes), kind)
+    }
+
+    #[test]
+    fn reads_regular_files() {
+        let fs = read(
+            tar_gz(&[("rust/main.rs", tar::EntryType::Regular, b"fn main() {}")]),
+            ArchiveKind::TarGz,
+        )
+        .unwrap();
+        assert_eq!(
+            fs.list_files().unwrap(),
+            vec![PathBuf::from("rust/main.rs")]
+        );
+
+        let fs = read(zip(&["./rust/main.rs"], &[]), ArchiveKind::Zip).unwrap();
+        assert_eq!(
+            fs.list_files().unwrap(),
+            vec![PathBuf::from("rust/main.rs")]
+        );
+    }
+
+    #[test]
+    fn refuses_paths_outside_the_archive() {
+        for path in ["../evil", "rust/../../evil", "/abs"] {
+            let tar = tar_gz(&[(path, tar::EntryType::Regular, b"data")]);
+            assert!(read(tar, ArchiveKind::TarGz).is_err(), "tar {path}");
+            assert!(
+                read(zip(&[path], &[]), ArchiveKind::Zip).is_err(),
+                "zip {path}"
+            );
+        }
+    }
+
+    #[test]
+    fn skips_symlinks() {
+        let tar = tar_gz(&[
+            ("link", tar::EntryType::Symlink, b""),
+            ("file", tar::EntryType::Regular, b"data"),
+        ]);
+        let fs = read(tar, ArchiveKind::TarGz).unwrap();
+        assert_eq!(fs.list_files().unwrap(), vec![PathBuf::from("file")]);
+
+        let fs = read(zip(&["file"], &["link"]), ArchiveKind::Zip).unwrap();
+        assert_eq!(fs.list_files().unwrap(), vec![PathBuf::from("file")]);
+    }
+
+    #[test]
+    fn safe_relative_paths() {
+        assert_eq!(
+            safe_relative_path(Path::new("./a/b")).unwrap(),
+            PathBuf::from("a/b")
+        );
+        assert!(safe_relative_path(Path::new("../a")).is_err());
+        assert!(safe_relative_path(Path::new("a/../../b")).is_err());
+        assert!(safe_relative_path(Path::new("/a")).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use git2::{Cred, CredentialType, RemoteCallbacks};
+use tracing::{debug, warn};
+
+use super::mirror::allow_partial_clone_extension;
+use crate::config::{GitConfig, GitHostConfig};
+
+static GIT_AUTH: OnceLock<GitConfig> = OnceLock::new();
+
+// Well-known token variables, checked after `BOILERMAKER_GIT_TOKEN_<HOST>`.
+const HOST_TOKEN_ENV_VARS: [(&str, &[&str]); 2] = [
+    ("github.com", &["GITHUB_TOKEN", "GH_TOKEN"]),
+    ("gitlab.com", &["GITLAB_TOKEN"]),
+];
+
+const DEFAULT_SSH_KEYS: [&str; 3] = ["id_ed25519", "id_ecdsa", "id_rsa"];
+
+// Set the git credentials from the system config. Call once at startup, before anything talks to a
+// remote; without it only the SSH agent, default keys, env tokens and credential helpers are used.
+// This also lets libgit2 open partial mirrors, which has to happen before any other git call.
+#[tracing::instrument]
+pub fn init_git_auth(config: Option<&GitConfig>) {
+    allow_partial_clone_extension();
+    if GIT_AUTH.set(config.cloned().unwrap_or_default()).is_err() {
+        warn!("Git credentials were already initialized");
+    }
+}
+
+fn git_auth() -> &'static GitConfig {
+    GIT_AUTH.get_or_init(GitConfig::default)
+}
+
+// The host of a repo URL, for both `https://host/path` and scp-like `git@host:path` URLs.
+#[tracing::instrument]
+pub fn url_host(url: &str) -> Option<String> {
+    let rest = match url.split_once("://") {
+        Some(("file", _)) => return None,
+        Some((_, rest)) => rest,
+        None if url.contains(':') => url,
+        None => return None,
+    };
+    let authority = rest.split('/').next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map(|(_, h)| h)
+        .unwrap_or(authority);
+    let host = host.split(':').next()?;
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
+
+fn host_config(host: &str) -> Option<&'static GitHostConfig> {
+    git_auth()
+        .hosts
+        .as_ref()?
+        .iter()
+        .find(|(h, _)| h.eq_ignore_ascii_case(host))
+        .map(|(_, c)| c)
+}
+
+// Hosts marked `private = true` in the config.
+#[tracing::instrument]
+pub fn is_private_host(host: &str) -> bool {
+    host_config(host).and_then(|c| c.private).unwrap_or(false)
+}
+
+fn host_env_var(host: &str) -> String {
+    let host: String = host
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("BOILERMAKER_GIT_TOKEN_{host}")
+}
+
+fn non_empty_env(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+// Find an HTTPS token for `host`: the host's `token_env` and `token` from the system config first,
+// then `BOILERMAKER_GIT_TOKEN_<HOST>` and the usual variables for GitHub and GitLab.
+fn host_token(host: &str) -> Option<(String, String)> {
+    let config = host_config(host);
+    let username = config
+        .and_then(|c| c.username.clone())
+        .unwrap_or_else(|| "x-access-token".to_owned());
+
+    let token = config
+        .and_then(|c| c.token_env.as_deref())
+        .and_then(non_empty_env)
+        .or_else(|| config.and_then(|c| c.token.clone()))
+        .or_else(|| non_empty_env(&host_env_var(host)))
+        .or_else(|| {
+            HOST_TOKEN_ENV_VARS
+                .iter()
+                .find(|(h, _)| *h == host)
+                .and_then(|(_, vars)| vars.iter().find_map(|v| non_empty_env(v)))
+        })?;
+
+    Some((username, token))
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+// Configured keys first, then the default keys in `~/.ssh`.
+fn ssh_key_paths() -> Vec<PathBuf> {
+    let mut keys: Vec<PathBuf> = git_auth()
+        .ssh_keys
+        .iter()
+        .flatten()
+        .map(|k| expand_home(k))
+        .collect();
+    if let Some(ssh_dir) = dirs::home_dir().map(|h| h.join(".ssh")) {
+        keys.extend(DEFAULT_SSH_KEYS.iter().map(|k| ssh_dir.join(k)));
+    }
+    keys.retain(|k| k.exists());
+    keys.dedup();
+    keys
+}
+
+// libgit2 calls the credentials callback again after every rejected attempt, so each method is
+// only offered once before giving up.
+#[derive(Default)]
+struct CredentialAttempts {
+    ssh_agent: bool,
+    ssh_key: usize,
+    token: bool,
+    helper: bool,
+    default: bool,
+}
+
+// Remote callbacks that authenticate with, in order: the SSH agent and SSH keys for SSH URLs, and
+// a token or git credential helper for HTTPS URLs.
+pub fn remote_callbacks<'a>() -> RemoteCallbacks<'a> {
+    let mut attempts = CredentialAttempts::default();
+    let ssh_keys = ssh_key_paths();
+
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username_from_url, allowed| {
+        let username = username_from_url.unwrap_or("git");
+
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(username);
+        }
+
+        if allowed.contains(CredentialType::SSH_KEY) {
+            if !attempts.ssh_agent {
+                attempts.ssh_agent = true;
+                debug!("Trying SSH agent for {url}");
+                if let Ok(cred) = Cred::ssh_key_from_agent(username) {
+                    return Ok(cred);
+                }
+            }
+            while let Some(key) = ssh_keys.get(attempts.ssh_key) {
+                attempts.ssh_key += 1;
+                debug!("Trying SSH key {} for {url}", key.display());
+                if let Ok(cred) = Cred::ssh_key(username, None, key, None) {
+                    return Ok(cred);
+                }
+            }
+        }
+
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            if !attempts.token {
+                attempts.token = true;
+                if let Some((user, token)) = url_host(url).and_then(|h| host_token(&h)) {
+                    debug!("Trying token for {url}");
+                    return Cred::userpass_plaintext(&user, &token);
+                }
+            }
+            if !attempts.helper {
+                attempts.helper = true;
+                if let Ok(config) = git2::Config::open_default() {
+                    debug!("Trying git credential helper for {url}");
+                    if let Ok(cred) = Cred::credential_helper(&config, url, username_from_url) {
+                        return Ok(cred);
+                    }
+                }
+            }
+        }
+
+        if allowed.contains(CredentialType::DEFAULT) && !attempts.default {
+            attempts.default = true;
+            return Cred::default();
+        }
+
+        Err(git2::Error::from_str(&format!(
+            "no working credentials for {url} (tried SSH agent/keys, tokens and credential helpers)"
+        )))
+    });
+
+    callbacks
+}
+
+// Environment for git CLI calls against `url`, so the git CLI authenticates like libgit2 does.
+// The git CLI already uses the SSH agent, default SSH keys and credential helpers on its own, this
+// adds the configured SSH keys and the host's token (passed through the environment, not args).
+#[tracing::instrument]
+pub fn git_cli_env(url: &str) -> Vec<(OsString, OsString)> {
+    let mut vars: Vec<(OsString, OsString)> = Vec::new();
+
+    let ssh_keys: Vec<PathBuf> = git_auth()
+        .ssh_keys
+        .iter()
+        .flatten()
+        .map(|k| expand_home(k))
+        .filter(|k| k.exists())
+        .collect();
+    if !ssh_keys.is_empty() && env::var_os("GIT_SSH_COMMAND").is_none() {
+        let identities = ssh_keys
+            .iter()
+            .map(|k| format!("-i '{}'", k.display().to_string().replace('\'', r"'\''")))
+            .collect::<Vec<_>>()
+            .join(" ");
+        vars.push(("GIT_SSH_COMMAND".into(), format!("ssh {identities}").into()));
+    }
+
+    if let Some(host) = url_host(url)
+        && let Some((username, token)) = host_token(&host)
+    {
+        let helper = r#"!f() { test "$1" = get && echo "username=$BOILERMAKER_GIT_USERNAME" && echo "password=$BOILERMAKER_GIT_PASSWORD"; }; f"#;
+        // Appended after any `GIT_CONFIG_*` entries the user already set.
+        let index = env::var("GIT_CONFIG_COUNT")
+            .ok()
+            .and_then(|count| count.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        vars.extend([
+            ("GIT_CONFIG_COUNT".into(), (index + 1).to_string().into()),
+            (
+                format!("GIT_CONFIG_KEY_{index}").into(),
+                "credential.helper".into(),
+            ),
+            (format!("GIT_CONFIG_VALUE_{index}").into(), helper.into()),
+            ("BOILERMAKER_GIT_USERNAME".into(), username.into()),
+            ("BOILERMAKER_GIT_PASSWORD".into(), token.into()),
+        ]);
+    }
+
+    vars
+}
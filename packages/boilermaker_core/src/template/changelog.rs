@@ -0,0 +1,225 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::Path;
+
+use color_eyre::Result;
+use git2::{Delta, DiffOptions, Oid, Repository, Sort, Tree};
+use serde::Serialize;
+
+use super::fs::TemplateFs;
+use super::short_sha;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Changelog {
+    // `None` when the template was installed before commits were tracked.
+    pub from: Option<String>,
+    pub to: String,
+    // The installed commit isn't in the history of `to` (force-pushed, or never recorded), so
+    // there are no commits to list and `files` compares the installed files instead.
+    pub diverged: bool,
+    pub commits: Vec<ChangelogCommit>,
+    pub files: Vec<FileChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangelogCommit {
+    pub sha: String,
+    pub summary: String,
+    pub author: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileChangeKind {
+    Added,
+    Modified,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    pub kind: FileChangeKind,
+    pub path: String,
+}
+
+impl Changelog {
+    pub fn count(&self, kind: FileChangeKind) -> usize {
+        self.files.iter().filter(|f| f.kind == kind).count()
+    }
+}
+
+impl fmt::Display for Changelog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let from = self.from.as_deref().map(short_sha);
+        let from = from.as_deref().unwrap_or("untracked");
+        if self.diverged {
+            writeln!(
+                f,
+                "{from} → {} (history diverged, comparing the installed files)",
+                short_sha(&self.to)
+            )?;
+        } else {
+            writeln!(
+                f,
+                "{from} → {} ({} commit(s))",
+                short_sha(&self.to),
+                self.commits.len()
+            )?;
+        }
+        for c in &self.commits {
+            writeln!(f, "  {} {} ({})", short_sha(&c.sha), c.summary, c.author)?;
+        }
+        writeln!(
+            f,
+            "Files: {} added, {} modified, {} removed",
+            self.count(FileChangeKind::Added),
+            self.count(FileChangeKind::Modified),
+            self.count(FileChangeKind::Removed)
+        )?;
+        for file in &self.files {
+            let marker = match file.kind {
+                FileChangeKind::Added => "A",
+                FileChangeKind::Modified => "M",
+                FileChangeKind::Removed => "D",
+            };
+            writeln!(f, "  {marker} {}", file.path)?;
+        }
+        Ok(())
+    }
+}
+
+// Collect the commits and file changes between `from` and `to`, limited to `subdir` when given.
+// Paths are reported relative to `subdir`.
+#[tracing::instrument(skip(repo))]
+pub fn changelog(
+    repo: &Repository,
+    from: &str,
+    to: &str,
+    subdir: Option<&str>,
+) -> Result<Changelog> {
+    let from_commit = repo.find_commit(Oid::from_str(from)?)?;
+    let to_commit = repo.find_commit(Oid::from_str(to)?)?;
+    let subdir = subdir.map(|s| s.trim_matches('/'));
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    walk.push(to_commit.id())?;
+    walk.hide(from_commit.id())?;
+
+    let mut commits = Vec::new();
+    for oid in walk {
+        let commit = repo.find_commit(oid?)?;
+        let parent_tree = match commit.parents().next() {
+            Some(parent) => Some(parent.tree()?),
+            None => None,
+        };
+        if count_changes(repo, parent_tree.as_ref(), &commit.tree()?, subdir)? == 0 {
+            continue;
+        }
+        commits.push(ChangelogCommit {
+            sha: commit.id().to_string(),
+            summary: commit.summary().unwrap_or("").to_owned(),
+            author: commit.author().name().unwrap_or("").to_owned(),
+        });
+    }
+
+    let mut opts = diff_options(subdir);
+    let diff = repo.diff_tree_to_tree(
+        Some(&from_commit.tree()?),
+        Some(&to_commit.tree()?),
+        Some(&mut opts),
+    )?;
+
+    let mut files = Vec::new();
+    for delta in diff.deltas() {
+        let (kind, file) = match delta.status() {
+            Delta::Added => (FileChangeKind::Added, delta.new_file()),
+            Delta::Deleted => (FileChangeKind::Removed, delta.old_file()),
+            _ => (FileChangeKind::Modified, delta.new_file()),
+        };
+        let Some(path) = file.path() else {
+            continue;
+        };
+        let path = match subdir {
+            Some(subdir) => path.strip_prefix(subdir).unwrap_or(path),
+            None => path,
+        };
+        files.push(FileChange {
+            kind,
+            path: path.to_string_lossy().to_string(),
+        });
+    }
+
+    Ok(Changelog {
+        from: Some(from.to_owned()),
+        to: to.to_owned(),
+        diverged: false,
+        commits,
+        files,
+    })
+}
+
+// Whether `to` has `from` in its history, so `changelog` can list the commits between them.
+#[tracing::instrument(skip(repo))]
+pub fn has_ancestor(repo: &Repository, to: &str, from: &str) -> bool {
+    let (Ok(from), Ok(to)) = (Oid::from_str(from), Oid::from_str(to)) else {
+        return false;
+    };
+    repo.find_commit(from).is_ok()
+        && (from == to || repo.graph_descendant_of(to, from).is_ok_and(|d| d))
+}
+
+// The changelog of a template whose installed commit isn't in the history of `to`: no commits,
+// just the files that differ between the `installed` and `new` template.
+#[tracing::instrument(skip(installed, new))]
+pub fn diverged_changelog(
+    from: Option<&str>,
+    to: &str,
+    installed: &dyn TemplateFs,
+    new: &dyn TemplateFs,
+) -> Result<Changelog> {
+    let old_files = installed.list_files()?;
+    let new_files = new.list_files()?;
+    let paths = old_files.iter().chain(&new_files).collect::<BTreeSet<_>>();
+
+    let mut files = Vec::new();
+    for path in paths {
+        let kind = match (installed.exists(path), new.exists(path)) {
+            (false, true) => FileChangeKind::Added,
+            (true, false) => FileChangeKind::Removed,
+            _ if installed.read(path)? != new.read(path)? => FileChangeKind::Modified,
+            _ => continue,
+        };
+        files.push(FileChange {
+            kind,
+            path: path.to_string_lossy().to_string(),
+        });
+    }
+
+    Ok(Changelog {
+        from: from.map(str::to_owned),
+        to: to.to_owned(),
+        diverged: true,
+        commits: Vec::new(),
+        files,
+    })
+}
+
+fn count_changes(
+    repo: &Repository,
+    old: Option<&Tree>,
+    new: &Tree,
+    subdir: Option<&str>,
+) -> Result<usize> {
+    let mut opts = diff_options(subdir);
+    let diff = repo.diff_tree_to_tree(old, Some(new), Some(&mut opts))?;
+    Ok(diff.deltas().len())
+}
+
+fn diff_options(subdir: Option<&str>) -> DiffOptions {
+    let mut opts = DiffOptions::new();
+    if let Some(subdir) = subdir.filter(|s| !s.is_empty()) {
+        opts.pathspec(Path::new(subdir));
+    }
+    opts
+}
@@ -0,0 +1,460 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use color_eyre::{Result, eyre::eyre};
+use git2::{ObjectType, Oid, Repository, TreeWalkMode, TreeWalkResult};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use super::short_sha;
+use crate::config::TemplateConfig;
+
+const TEMPLATE_CONFIG_FILE: &str = "boilermaker.toml";
+
+// Read-only access to the files of a template, wherever they are stored. Paths are relative to
+// the template root (the dir holding boilermaker.toml) and never include `.git`.
+pub trait TemplateFs: fmt::Debug + Send + Sync {
+    // Every file of the template, sorted by path.
+    fn list_files(&self) -> Result<Vec<PathBuf>>;
+
+    fn exists(&self, path: &Path) -> bool;
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>>;
+
+    fn is_executable(&self, _path: &Path) -> bool {
+        false
+    }
+
+    // Where `path` lives, for messages.
+    fn describe(&self, path: &Path) -> String {
+        path.display().to_string()
+    }
+
+    // Read a file as text. Returns `None` for binary (non UTF-8) files.
+    fn read_text(&self, path: &Path) -> Result<Option<String>> {
+        Ok(String::from_utf8(self.read(path)?).ok())
+    }
+}
+
+// A template in a directory on disk. Symlinks are skipped so nothing outside the template is read.
+#[derive(Debug, Clone)]
+pub struct DirFs {
+    root: PathBuf,
+}
+
+impl DirFs {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        DirFs { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl TemplateFs for DirFs {
+    fn list_files(&self) -> Result<Vec<PathBuf>> {
+        if !self.root.is_dir() {
+            return Err(eyre!("💥 Template dir not found: {}", self.root.display()));
+        }
+
+        let mut files = Vec::new();
+        let entries = WalkDir::new(&self.root)
+            .min_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| e.file_name() != ".git");
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type().is_file() {
+                files.push(entry.path().strip_prefix(&self.root)?.to_path_buf());
+            }
+        }
+        Ok(files)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.root.join(path).is_file()
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        Ok(fs::read(self.root.join(path))?)
+    }
+
+    #[cfg(unix)]
+    fn is_executable(&self, path: &Path) -> bool {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(self.root.join(path)).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+    }
+
+    fn describe(&self, path: &Path) -> String {
+        self.root.join(path).display().to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GitTreeFile {
+    oid: Oid,
+    executable: bool,
+}
+
+// A template read straight from the objects of a commit, without checking it out. Partial mirrors
+// need the blobs fetched first, see `prefetch_blobs`.
+pub struct GitTreeFs {
+    repo: Mutex<Repository>,
+    commit_sha: String,
+    subdir: Option<String>,
+    files: BTreeMap<PathBuf, GitTreeFile>,
+}
+
+impl fmt::Debug for GitTreeFs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitTreeFs")
+            .field("commit_sha", &self.commit_sha)
+            .field("subdir", &self.subdir)
+            .field("files", &self.files.len())
+            .finish()
+    }
+}
+
+impl GitTreeFs {
+    // The files of `commit_sha` in the bare repo at `repo_path`, limited to `subdir` when given.
+    #[tracing::instrument]
+    pub fn open(repo_path: &Path, commit_sha: &str, subdir: Option<&str>) -> Result<Self> {
+        let repo = Repository::open_bare(repo_path)?;
+        let subdir = subdir
+            .map(|s| s.trim_matches('/'))
+            .filter(|s| !s.is_empty());
+
+        let mut files = BTreeMap::new();
+        {
+            let commit = repo.revparse_single(commit_sha)?.peel_to_commit()?;
+            let mut tree = commit.tree()?;
+            if let Some(subdir) = subdir {
+                let entry = tree.get_path(Path::new(subdir)).map_err(|_| {
+                    eyre!(
+                        "💥 Can't find `{subdir}` in commit {}",
+                        short_sha(commit_sha)
+                    )
+                })?;
+                tree = entry.to_object(&repo)?.peel_to_tree()?;
+            }
+
+            // Symlinks (0o120000) and submodules (commits) are left out, like on checkout-less
+            // installs from any other location.
+            tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+                if entry.kind() == Some(ObjectType::Blob) && entry.filemode() != 0o120000 {
+                    let name = String::from_utf8_lossy(entry.name_bytes()).to_string();
+                    files.insert(
+                        Path::new(dir).join(name),
+                        GitTreeFile {
+                            oid: entry.id(),
+                            executable: entry.filemode() == 0o100755,
+                        },
+                    );
+                }
+                TreeWalkResult::Ok
+            })?;
+        }
+
+        Ok(GitTreeFs {
+            repo: Mutex::new(repo),
+            commit_sha: commit_sha.to_owned(),
+            subdir: subdir.map(|s| s.to_owned()),
+            files,
+        })
+    }
+}
+
+impl TemplateFs for GitTreeFs {
+    fn list_files(&self) -> Result<Vec<PathBuf>> {
+        Ok(self.files.keys().cloned().collect())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let file = self
+            .files
+            .get(path)
+            .ok_or_else(|| eyre!("💥 File not found: {}", self.describe(path)))?;
+        let repo = self.repo.lock().unwrap();
+        let blob = repo.find_blob(file.oid).map_err(|e| {
+            eyre!(
+                "💥 Can't read {} from the git mirror: {e}",
+                self.describe(path)
+            )
+        })?;
+        Ok(blob.content().to_vec())
+    }
+
+    fn is_executable(&self, path: &Path) -> bool {
+        self.files.get(path).is_some_and(|f| f.executable)
+    }
+
+    fn describe(&self, path: &Path) -> String {
+        let path = match &self.subdir {
+            Some(subdir) => Path::new(subdir).join(path),
+            None => path.to_path_buf(),
+        };
+        format!("{}:{}", short_sha(&self.commit_sha), path.display())
+    }
+}
+
+#[derive(Clone)]
+struct MemoryFile {
+    data: Vec<u8>,
+    executable: bool,
+}
+
+// A template held in memory: unpacked archives, content from the SQLite index, or files built up
+// by hand.
+#[derive(Clone, Default)]
+pub struct MemoryFs {
+    files: BTreeMap<PathBuf, MemoryFile>,
+}
+
+impl fmt::Debug for MemoryFs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryFs")
+            .field("files", &self.files.len())
+            .finish()
+    }
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        MemoryFs::default()
+    }
+
+    // Add a file. Absolute paths and `..` components are refused, so writing the files out again
+    // can't escape the target dir.
+    pub fn insert(&mut self, path: &Path, data: Vec<u8>, executable: bool) -> Result<()> {
+        let path = safe_relative_path(path)?;
+        if path.as_os_str().is_empty() {
+            return Err(eyre!("💥 Empty template file path"));
+        }
+        self.files.insert(path, MemoryFile { data, executable });
+        Ok(())
+    }
+
+    // The files under `dir`, relative to it.
+    pub fn subdir(&self, dir: &Path) -> MemoryFs {
+        let files = self
+            .files
+            .iter()
+            .filter_map(|(path, file)| {
+                let rel = path.strip_prefix(dir).ok()?;
+                Some((rel.to_path_buf(), file.clone()))
+            })
+            .filter(|(rel, _)| !rel.as_os_str().is_empty())
+            .collect();
+        MemoryFs { files }
+    }
+
+    // The dir everything is in, for archives that wrap the template in one (like GitHub's source
+    // downloads do).
+    pub fn single_top_level_dir(&self) -> Option<PathBuf> {
+        let mut tops = BTreeSet::new();
+        for path in self.files.keys() {
+            let mut components = path.components();
+            let top = components.next()?;
+            components.next()?;
+            tops.insert(top.as_os_str().to_owned());
+        }
+        match tops.len() {
+            1 => tops.pop_first().map(PathBuf::from),
+            _ => None,
+        }
+    }
+
+    // Build a template from indexed `(path, content)` pairs. Returns `None` when any file has no
+    // content (binary files aren't indexed). Older indexes stored the absolute paths of the clone,
+    // those are made relative to the dir holding the top-level boilermaker.toml.
+    #[tracing::instrument(skip(files))]
+    pub fn from_indexed_files(files: Vec<(String, Option<String>)>) -> Result<Option<Self>> {
+        if files.is_empty() || files.iter().any(|(_, content)| content.is_none()) {
+            return Ok(None);
+        }
+
+        let root = files
+            .iter()
+            .map(|(path, _)| Path::new(path))
+            .filter(|p| p.file_name().is_some_and(|n| n == TEMPLATE_CONFIG_FILE))
+            .min_by_key(|p| p.components().count())
+            .and_then(|p| p.parent())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let mut memory_fs = MemoryFs::new();
+        for (path, content) in files {
+            let path = Path::new(&path);
+            let rel = path.strip_prefix(&root).unwrap_or(path);
+            if rel.components().any(|c| !matches!(c, Component::Normal(_))) {
+                return Err(eyre!("💥 Invalid path in source index: {}", path.display()));
+            }
+            memory_fs.insert(rel, content.unwrap_or_default().into_bytes(), false)?;
+        }
+        Ok(Some(memory_fs))
+    }
+}
+
+impl TemplateFs for MemoryFs {
+    fn list_files(&self) -> Result<Vec<PathBuf>> {
+        Ok(self.files.keys().cloned().collect())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        self.files
+            .get(path)
+            .map(|f| f.data.clone())
+            .ok_or_else(|| eyre!("💥 File not found: {}", path.display()))
+    }
+
+    fn is_executable(&self, path: &Path) -> bool {
+        self.files.get(path).is_some_and(|f| f.executable)
+    }
+}
+
+// Make `path` relative, refusing absolute paths and `..` components.
+#[tracing::instrument]
+pub fn safe_relative_path(path: &Path) -> Result<PathBuf> {
+    let mut rel = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => rel.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(eyre!(
+                    "💥 Path escapes the template directory: {}",
+                    path.display()
+                ));
+            }
+        }
+    }
+    Ok(rel)
+}
+
+#[tracing::instrument]
+pub fn read_template_config(template_fs: &dyn TemplateFs) -> Result<TemplateConfig> {
+    let path = Path::new(TEMPLATE_CONFIG_FILE);
+    if !template_fs.exists(path) {
+        return Err(eyre!(
+            "❗ Config file not found at `{}`.",
+            template_fs.describe(path)
+        ));
+    }
+    let content = template_fs
+        .read_text(path)?
+        .ok_or_else(|| eyre!("💥 {} is not valid UTF-8", template_fs.describe(path)))?;
+    Ok(toml::from_str(&content)?)
+}
+
+// The languages a template has: its top-level dirs, leaving out hidden ones like `.github`.
+#[tracing::instrument]
+pub fn template_langs(template_fs: &dyn TemplateFs) -> Result<Vec<String>> {
+    let langs = template_fs
+        .list_files()?
+        .iter()
+        .filter(|f| f.components().count() > 1)
+        .filter_map(|f| f.components().next())
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .filter(|lang| !lang.starts_with('.'))
+        .collect::<BTreeSet<_>>();
+    Ok(langs.into_iter().collect())
+}
+
+// Same hash as `sha256_hash_dir` gives for the same files on disk.
+#[tracing::instrument]
+pub fn sha256_hash_fs(template_fs: &dyn TemplateFs) -> Result<String> {
+    let mut hasher = Sha256::new();
+    for path in template_fs.list_files()? {
+        hasher.update(path.to_string_lossy().as_bytes());
+        hasher.update([0u8]);
+        hasher.update(template_fs.read(&path)?);
+        hasher.update([0u8]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn write_file(dest: &Path, data: &[u8], executable: bool) -> Result<()> {
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(dest, data)?;
+
+    #[cfg(unix)]
+    if executable {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dest, fs::Permissions::from_mode(0o755))?;
+    }
+    #[cfg(not(unix))]
+    let _ = executable;
+
+    Ok(())
+}
+
+// Write every file of the template under `dest`, as is.
+#[tracing::instrument]
+pub fn write_to_dir(template_fs: &dyn TemplateFs, dest: &Path) -> Result<()> {
+    fs::create_dir_all(dest)?;
+    for path in template_fs.list_files()? {
+        let data = template_fs.read(&path)?;
+        write_file(&dest.join(&path), &data, template_fs.is_executable(&path))?;
+    }
+    Ok(())
+}
+
+// Render the `lang` dir of the template into `dest`. Text files go through Jinja, binary files
+// (images, fonts, ...) are copied as they are.
+#[tracing::instrument(skip(ctx))]
+pub fn render_to_dir(
+    template_fs: &dyn TemplateFs,
+    lang: &str,
+    ctx: HashMap<String, String>,
+    dest: &Path,
+) -> Result<()> {
+    let files = template_fs
+        .list_files()?
+        .into_iter()
+        .filter(|p| p.starts_with(lang))
+        .collect::<Vec<_>>();
+    if files.is_empty() {
+        return Err(eyre!("💥 Template has no `{lang}` directory."));
+    }
+
+    let mut jinja = minijinja::Environment::new();
+    let ctx = minijinja::context! { ..ctx };
+
+    for path in files {
+        let rel = path.strip_prefix(lang)?;
+        let target = dest.join(rel);
+        let executable = template_fs.is_executable(&path);
+
+        let data = template_fs.read(&path)?;
+        let content = match String::from_utf8(data) {
+            Ok(content) => content,
+            Err(e) => {
+                write_file(&target, e.as_bytes(), executable)?;
+                continue;
+            }
+        };
+
+        // The name picks the auto-escaping, e.g. HTML escaping for `.html` files.
+        let name = rel.to_string_lossy().to_string();
+        jinja.add_template_owned(name.clone(), content)?;
+        let rendered = jinja.get_template(&name)?.render(&ctx)?;
+        write_file(&target, rendered.as_bytes(), executable)?;
+    }
+
+    Ok(())
+}
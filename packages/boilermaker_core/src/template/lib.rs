@@ -1,20 +1,27 @@
-use std::{collections::HashMap, env, fs, path::PathBuf};
+use std::{env, fs, path::PathBuf};
 
 use color_eyre::{Result, eyre::eyre};
 use dirs;
-use fs_extra::dir::{CopyOptions, copy};
-use git2::{FetchOptions, Repository, build::RepoBuilder};
-use minijinja;
-
+use git2::{Direction, Remote, Repository};
+
+use super::auth::remote_callbacks;
+use super::fs::{TemplateFs, write_to_dir};
+use super::mirror::{
+    checkout_commit, get_mirror_path, lock_mirror_file, mirror_key, mirror_lock, open_mirror,
+    prefetch_blobs, update_mirror,
+};
 use crate::config::TemplateConfig;
 pub use crate::config::get_template_config;
-use crate::util::file::list_dir;
 
 #[derive(Debug)]
 pub struct CloneContext {
     pub url: String,
     pub dest: Option<PathBuf>,
     pub branch: Option<String>,
+    // A branch, tag or commit. Takes precedence over `branch` when both are set.
+    pub git_ref: Option<String>,
+    // Only check out (and where possible, only fetch) this path of the repo.
+    pub subdir: Option<String>,
 }
 
 impl CloneContext {
@@ -23,35 +30,254 @@ impl CloneContext {
             url: url.to_owned(),
             branch,
             dest,
+            git_ref: None,
+            subdir: None,
         }
     }
+
+    pub fn with_ref(mut self, git_ref: Option<String>) -> Self {
+        self.git_ref = git_ref;
+        self
+    }
+
+    pub fn with_subdir(mut self, subdir: Option<String>) -> Self {
+        self.subdir = subdir;
+        self
+    }
 }
 
-#[tracing::instrument]
-pub async fn clone_repo(ctx: &CloneContext) -> Result<Repository> {
-    let mut fetch_opts = FetchOptions::new();
-    fetch_opts.depth(1);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefKind {
+    Branch,
+    Tag,
+    Commit,
+}
 
-    let mut repo_builder = RepoBuilder::new();
-    repo_builder.fetch_options(fetch_opts);
+impl RefKind {
+    // Tags and commits don't move (or shouldn't), so templates installed from them stay put.
+    pub fn is_pinned(&self) -> bool {
+        !matches!(self, RefKind::Branch)
+    }
+}
 
-    if let Some(branch) = &ctx.branch {
-        repo_builder.branch(branch);
+impl std::fmt::Display for RefKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RefKind::Branch => write!(f, "branch"),
+            RefKind::Tag => write!(f, "tag"),
+            RefKind::Commit => write!(f, "commit"),
+        }
     }
+}
+
+pub struct ClonedRepo {
+    // The bare mirror the commit was fetched into. It has the full history of the remote.
+    pub repo: Repository,
+    pub git_ref: String,
+    pub ref_kind: RefKind,
+    pub commit_sha: String,
+}
+
+impl std::fmt::Debug for ClonedRepo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClonedRepo")
+            .field("git_ref", &self.git_ref)
+            .field("ref_kind", &self.ref_kind)
+            .field("commit_sha", &self.commit_sha)
+            .finish()
+    }
+}
+
+// Fetch `ctx.url` into its persistent mirror and resolve the requested ref (or the remote's
+// default branch) to a commit. Nothing is checked out, read the files with `GitTreeFs` or write
+// them out with `clone_repo`.
+#[tracing::instrument]
+pub async fn fetch_repo(ctx: &CloneContext) -> Result<ClonedRepo> {
+    let lock = mirror_lock(&get_mirror_path(&ctx.url)?);
+    let _guard = lock.lock().await;
+
+    // libgit2 and the git CLI block, so the fetch runs on the blocking pool. The mirror stays
+    // locked until it's done.
+    let url = ctx.url.to_owned();
+    let wanted = ctx.git_ref.clone().or(ctx.branch.clone());
+    let subdir = ctx.subdir.clone();
+    tokio::task::spawn_blocking(move || fetch_mirror(&url, wanted.as_ref(), subdir.as_deref()))
+        .await?
+}
+
+fn fetch_mirror(url: &str, wanted: Option<&String>, subdir: Option<&str>) -> Result<ClonedRepo> {
+    let _lock = lock_mirror_file(&get_mirror_path(url)?)?;
+    let repo = update_mirror(url, subdir.is_some())?;
+
+    let (git_ref, ref_kind, commit_sha) = {
+        let (git_ref, ref_kind) = resolve_ref(&repo, wanted)?;
+        let commit = match ref_kind {
+            RefKind::Branch => repo
+                .find_reference(&format!("refs/heads/{git_ref}"))?
+                .peel_to_commit()?,
+            RefKind::Tag => repo
+                .find_reference(&format!("refs/tags/{git_ref}"))?
+                .peel_to_commit()?,
+            RefKind::Commit => repo.revparse_single(&git_ref)?.peel_to_commit()?,
+        };
+
+        prefetch_blobs(&repo, &commit, subdir)?;
+
+        (git_ref, ref_kind, commit.id().to_string())
+    };
+
+    Ok(ClonedRepo {
+        repo,
+        git_ref,
+        ref_kind,
+        commit_sha,
+    })
+}
 
+// Like `fetch_repo`, then check out the files of the commit into `ctx.dest`. The checkout has no
+// .git dir of its own. With `ctx.subdir` set, only that path ends up in `ctx.dest` (still at
+// `ctx.dest/<subdir>`).
+#[tracing::instrument]
+pub async fn clone_repo(ctx: &CloneContext) -> Result<ClonedRepo> {
     let dir = match &ctx.dest {
         Some(d) => d.into(),
         None => env::temp_dir(),
     };
 
-    let repo = repo_builder.clone(&ctx.url, &dir)?;
+    let cloned = fetch_repo(ctx).await?;
+    let subdir = ctx.subdir.clone();
+    let mirror_path = get_mirror_path(&ctx.url)?;
+    tokio::task::spawn_blocking(move || {
+        {
+            // Checking out of a partial mirror fetches the missing blobs into it.
+            let _lock = lock_mirror_file(&mirror_path)?;
+            let commit = cloned
+                .repo
+                .find_commit(git2::Oid::from_str(&cloned.commit_sha)?)?;
+            checkout_commit(&cloned.repo, &commit, &dir, subdir.as_deref())?;
+        }
+        Ok(cloned)
+    })
+    .await?
+}
+
+// Work out what `wanted` refers to in a freshly fetched mirror.
+#[tracing::instrument(skip(repo))]
+fn resolve_ref(repo: &Repository, wanted: Option<&String>) -> Result<(String, RefKind)> {
+    let Some(wanted) = wanted else {
+        let head = repo.find_reference("HEAD")?;
+        let target = head
+            .symbolic_target()
+            .ok_or_else(|| eyre!("💥 Can't find the remote's default branch"))?;
+        let name = target.strip_prefix("refs/heads/").unwrap_or(target);
+        return Ok((name.to_owned(), RefKind::Branch));
+    };
 
-    Ok(repo)
+    let wanted = wanted.strip_prefix("refs/heads/").unwrap_or(wanted);
+    if repo.find_reference(&format!("refs/heads/{wanted}")).is_ok() {
+        Ok((wanted.to_owned(), RefKind::Branch))
+    } else if repo.find_reference(&format!("refs/tags/{wanted}")).is_ok() {
+        Ok((wanted.to_owned(), RefKind::Tag))
+    } else if is_commit_sha(wanted) && repo.revparse_single(wanted).is_ok() {
+        Ok((wanted.to_owned(), RefKind::Commit))
+    } else {
+        Err(eyre!(
+            "💥 Can't find branch, tag or commit `{wanted}` on remote"
+        ))
+    }
+}
+
+// Look up the commit `git_ref` (or the default branch) currently points to on the remote. This only
+// lists remote refs, nothing is fetched. Returns `None` when `git_ref` is a commit, which can't move.
+#[tracing::instrument]
+pub fn find_remote_commit(url: &str, git_ref: Option<&str>) -> Result<Option<String>> {
+    let mut remote = Remote::create_detached(url)?;
+    let connection = remote.connect_auth(Direction::Fetch, Some(remote_callbacks()), None)?;
+
+    let git_ref = match git_ref {
+        Some(git_ref) => git_ref.to_owned(),
+        None => {
+            let default_branch = connection.default_branch()?;
+            let default_branch = default_branch
+                .as_str()
+                .ok_or_else(|| eyre!("💥 Remote default branch is not valid UTF-8"))?;
+            default_branch
+                .strip_prefix("refs/heads/")
+                .unwrap_or(default_branch)
+                .to_owned()
+        }
+    };
+
+    // Annotated tags are listed twice; the `^{}` entry is the commit the tag points to.
+    let candidates = [
+        format!("refs/heads/{git_ref}"),
+        format!("refs/tags/{git_ref}^{{}}"),
+        format!("refs/tags/{git_ref}"),
+    ];
+    let heads = connection.list()?;
+    let found = candidates.iter().find_map(|name| {
+        heads
+            .iter()
+            .find(|h| h.name() == name)
+            .map(|h| h.oid().to_string())
+    });
+
+    match found {
+        Some(sha) => Ok(Some(sha)),
+        None if is_commit_sha(&git_ref) => Ok(None),
+        None => Err(eyre!("💥 Can't find `{git_ref}` on remote {url}")),
+    }
+}
+
+// Count how many commits `to` is ahead of `from`. Only possible when both commits are available
+// locally, i.e. in the repo itself for local repos or in the mirror from an earlier fetch.
+#[tracing::instrument]
+pub fn count_commits_between(url: &str, from: &str, to: &str) -> Option<usize> {
+    let local_repo = if is_local_url(url) {
+        Repository::open(url.strip_prefix("file://").unwrap_or(url)).ok()
+    } else {
+        None
+    };
+    let repo = local_repo.or_else(|| open_mirror(url))?;
+    let from = git2::Oid::from_str(from).ok()?;
+    let to = git2::Oid::from_str(to).ok()?;
+    let (ahead, _behind) = repo.graph_ahead_behind(to, from).ok()?;
+    Some(ahead)
+}
+
+#[tracing::instrument]
+pub fn is_commit_sha(s: &str) -> bool {
+    (4..=40).contains(&s.len()) && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[tracing::instrument]
+pub fn is_local_url(url: &str) -> bool {
+    url.starts_with("file://") || !url.contains("://") && !url.contains('@')
+}
+
+// Split `<repo>@<ref>` into its parts. SSH-style URLs (`git@host:org/repo`) and URLs with user
+// info (`https://user@host/org/repo`) are left alone unless they have a trailing `@<ref>` too.
+#[tracing::instrument]
+pub fn split_repo_ref(s: &str) -> (String, Option<String>) {
+    if let Some((repo, git_ref)) = s.rsplit_once('@') {
+        let repo_path = repo.split_once("://").map(|(_, rest)| rest).unwrap_or(repo);
+        let has_path = repo_path.contains('/') || repo_path.contains(':');
+        if has_path && !git_ref.is_empty() && !git_ref.contains(':') {
+            return (repo.to_owned(), Some(git_ref.to_owned()));
+        }
+    }
+    (s.to_owned(), None)
+}
+
+#[tracing::instrument]
+pub fn short_sha(sha: &str) -> String {
+    sha.chars().take(7).collect()
 }
 
 #[tracing::instrument]
 pub fn make_name_from_url(url: &str) -> String {
-    url.split('/')
+    url.trim_end_matches('/')
+        .split('/')
         .next_back()
         .unwrap()
         .split('.')
@@ -62,7 +288,10 @@ pub fn make_name_from_url(url: &str) -> String {
 
 #[tracing::instrument]
 pub fn make_tmp_dir_from_url(url: &str) -> PathBuf {
-    env::temp_dir().join(make_name_from_url(url))
+    env::temp_dir()
+        .join("boilermaker")
+        .join("checkouts")
+        .join(mirror_key(url))
 }
 
 #[tracing::instrument]
@@ -141,7 +370,7 @@ pub fn create_template_dir(name: &str) -> Result<PathBuf> {
 }
 
 #[tracing::instrument]
-pub async fn install_template(src_path: &PathBuf, dest_path: &PathBuf) -> Result<()> {
+pub async fn install_template(template_fs: &dyn TemplateFs, dest_path: &PathBuf) -> Result<()> {
     if dest_path.exists() {
         return Err(eyre!(
             "💥 Template dir path exists: {}",
@@ -153,29 +382,12 @@ pub async fn install_template(src_path: &PathBuf, dest_path: &PathBuf) -> Result
         return Err(eyre!("💥 Failed to create template directory: {e}"));
     }
 
-    let mut options = CopyOptions::new();
-    options.content_only = true;
-
-    let src = src_path
-        .clone()
-        .into_os_string()
-        .into_string()
-        .map_err(|e| eyre!("💥 Invalid source path: {:?}", e))?;
-
-    let dest = dest_path
-        .clone()
-        .into_os_string()
-        .into_string()
-        .map_err(|e| eyre!("💥 Invalid destination path: {:?}", e))?;
-
-    if let Err(e) = copy(src, dest, &options) {
+    if let Err(e) = write_to_dir(template_fs, dest_path) {
         return Err(eyre!(
             "💥 Failed to move project to template directory: {e}"
         ));
     }
 
-    // TODO: discuss keeping the tmp dir after install for any purpose, otherwise burn it
-
     Ok(())
 }
 
@@ -215,39 +427,3 @@ pub async fn create_project_dir(
 
     Ok(project_dir)
 }
-
-//TODO: add setting to warn from sys_config on directory in paths vec
-//NOTE: for now, just skip
-#[tracing::instrument]
-pub async fn render_template_files(
-    paths: Vec<PathBuf>,
-    ctx: HashMap<String, String>,
-) -> Result<()> {
-    let mut jinja = minijinja::Environment::new();
-    let ctx = minijinja::context! { ..ctx.to_owned() };
-
-    for path in paths {
-        if path.is_file() {
-            let name = path.file_name().unwrap().to_str().unwrap().to_string();
-            let content = fs::read_to_string(&path)?;
-            jinja.add_template_owned(name.clone(), content)?;
-
-            let template = jinja.get_template(&name)?;
-            let rendered = template.render(&ctx)?;
-
-            fs::write(&path, rendered)?;
-        }
-    }
-
-    Ok(())
-}
-
-#[tracing::instrument]
-pub async fn list_template_files(dir: &PathBuf) -> Result<Vec<PathBuf>> {
-    let files = list_dir(dir)
-        .await?
-        .into_iter()
-        .filter(|p| p.is_file() && !p.to_str().unwrap_or("").contains(".git"))
-        .collect::<Vec<_>>();
-    Ok(files)
-}
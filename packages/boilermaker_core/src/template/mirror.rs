@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex, Once};
+use std::time::{Instant, SystemTime};
+
+use color_eyre::{Result, eyre::eyre};
+use git2::{
+    AutotagOption, Commit, Direction, FetchOptions, ObjectType, Repository, TreeWalkMode,
+    TreeWalkResult, build::CheckoutBuilder,
+};
+use lazy_static::lazy_static;
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+use super::auth::{git_cli_env, remote_callbacks};
+use crate::util::crypto::sha256_hash_string;
+use crate::util::file::get_boilermaker_dir;
+
+lazy_static! {
+    // One lock per mirror so concurrent installs/updates of the same repo don't race on git locks.
+    static ref MIRROR_LOCKS: Mutex<HashMap<PathBuf, Arc<tokio::sync::Mutex<()>>>> =
+        Mutex::new(HashMap::new());
+    static ref GIT_CLI_AVAILABLE: bool = Command::new("git")
+        .arg("--version")
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+}
+
+static ALLOW_PARTIAL_CLONE: Once = Once::new();
+
+const MIRROR_REFSPECS: [&str; 2] = ["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"];
+
+#[tracing::instrument]
+pub fn get_mirror_cache_dir() -> Result<PathBuf> {
+    Ok(get_boilermaker_dir()?.join("cache").join("git"))
+}
+
+// Normalize a repo URL so that e.g. `https://github.com/Org/Repo.git`, `https://GitHub.com/Org/Repo/`
+// and `git@github.com:Org/Repo` all map to `github.com/Org/Repo`. Only the host is case-insensitive:
+// paths are kept as they are, so `Org/Repo` and `org/repo` (or two local dirs) stay apart.
+#[tracing::instrument]
+pub fn normalize_repo_url(url: &str) -> String {
+    let url = url.trim();
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => {
+            let rest = without_user(rest);
+            rest.split_at(rest.find('/').unwrap_or(rest.len()))
+        }
+        // `user@host:path`, or a local path.
+        None => match without_user(url).split_once(':') {
+            Some((host, path)) if !host.contains('/') => (host, path),
+            _ => ("", url),
+        },
+    };
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if host.is_empty() {
+        path.to_owned()
+    } else {
+        format!("{}/{}", host.to_lowercase(), path.trim_start_matches('/'))
+    }
+}
+
+fn without_user(url: &str) -> &str {
+    match url.split_once('@') {
+        Some((user, rest)) if !user.contains('/') => rest,
+        _ => url,
+    }
+}
+
+// A readable, filesystem-safe key for a repo URL. The hash suffix keeps repos that share a final
+// path segment (or sanitize to the same string) apart.
+#[tracing::instrument]
+pub fn mirror_key(url: &str) -> String {
+    let normalized = normalize_repo_url(url);
+    let readable: String = normalized
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let readable = readable.trim_matches('_');
+    let hash = sha256_hash_string(&normalized);
+    format!("{}-{}", readable, &hash[..12])
+}
+
+#[tracing::instrument]
+pub fn get_mirror_path(url: &str) -> Result<PathBuf> {
+    Ok(get_mirror_cache_dir()?.join(mirror_key(url)))
+}
+
+#[tracing::instrument]
+pub fn mirror_lock(path: &Path) -> Arc<tokio::sync::Mutex<()>> {
+    let mut locks = MIRROR_LOCKS.lock().unwrap();
+    locks.entry(path.to_path_buf()).or_default().clone()
+}
+
+// `mirror_lock` only keeps tasks of this process apart. This advisory lock on `<mirror>.lock`
+// keeps other processes (another `boil`, the desktop app) out while the mirror is written, until
+// the returned file is dropped.
+#[tracing::instrument]
+pub fn lock_mirror_file(path: &Path) -> Result<File> {
+    let mut lock_path = path.as_os_str().to_owned();
+    lock_path.push(".lock");
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)?;
+    file.lock()?;
+    Ok(file)
+}
+
+// Partial clones are marked with `extensions.partialClone`, which libgit2 refuses to open unless
+// told it's supported. libgit2 can't fetch missing blobs itself, so the git CLI fetches them first
+// (see `prefetch_blobs`), after which libgit2 reads them like any other object.
+//
+// Only called from `init_git_auth`, at startup before anything uses libgit2.
+pub(crate) fn allow_partial_clone_extension() {
+    ALLOW_PARTIAL_CLONE.call_once(|| {
+        // SAFETY: `set_extensions` changes libgit2's global state and must not race with other
+        // libgit2 calls. `init_git_auth` runs once at startup, before any repository is opened.
+        if let Err(e) = unsafe { git2::opts::set_extensions(&["partialclone"]) } {
+            warn!("Can't enable partial clone support in libgit2: {e}");
+        }
+    });
+}
+
+// Partial mirrors only have the blobs that were needed so far. Anything reading file contents
+// from them has to `prefetch_blobs` first, libgit2 can't fetch missing blobs on demand.
+#[tracing::instrument(skip(repo))]
+pub fn is_partial_mirror(repo: &Repository) -> bool {
+    repo.config()
+        .and_then(|c| c.get_bool("remote.origin.promisor"))
+        .unwrap_or(false)
+}
+
+fn make_partial_mirror(repo: &Repository) -> Result<()> {
+    let mut config = repo.config()?;
+    config.set_i32("core.repositoryformatversion", 1)?;
+    config.set_str("extensions.partialClone", "origin")?;
+    config.set_bool("remote.origin.promisor", true)?;
+    config.set_str("remote.origin.partialclonefilter", "blob:none")?;
+    Ok(())
+}
+
+// Run the git CLI with the credentials for `url`.
+fn run_git<I, S>(url: &str, args: I) -> Result<()>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = Command::new("git")
+        .args(args)
+        .envs(git_cli_env(url))
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "💥 git failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+// Open (or create) the bare mirror for `url` and fetch all branches and tags into it. Fetches are
+// incremental: only objects the mirror doesn't have yet are transferred.
+//
+// With `sparse`, a new mirror is created as a blob-less partial clone (when the git CLI is
+// available, libgit2 doesn't support partial clones), so only the blobs of the paths that are
+// actually checked out get downloaded.
+#[tracing::instrument]
+pub fn update_mirror(url: &str, sparse: bool) -> Result<Repository> {
+    let path = get_mirror_path(url)?;
+    let repo = match Repository::open_bare(&path) {
+        Ok(repo) => repo,
+        Err(_) => {
+            debug!("Creating git mirror for {url} at {}", path.display());
+            fs::create_dir_all(&path)?;
+            let repo = Repository::init_bare(&path)?;
+            repo.remote("origin", url)?;
+            if sparse && *GIT_CLI_AVAILABLE {
+                make_partial_mirror(&repo)?;
+            } else if sparse {
+                debug!("git CLI not found, falling back to a full fetch for {url}");
+            }
+            repo
+        }
+    };
+    let partial = is_partial_mirror(&repo);
+    let size_before = dir_size(&path);
+    let started = Instant::now();
+
+    {
+        let mut remote = repo.find_remote("origin")?;
+        if remote.url() != Some(url) {
+            repo.remote_set_url("origin", url)?;
+            remote = repo.find_remote("origin")?;
+        }
+
+        let default_branch = {
+            let connection =
+                remote.connect_auth(Direction::Fetch, Some(remote_callbacks()), None)?;
+            let default_branch = connection.default_branch()?;
+            default_branch
+                .as_str()
+                .ok_or_else(|| eyre!("💥 Remote default branch is not valid UTF-8"))?
+                .to_owned()
+        };
+
+        if partial {
+            let mut args = vec![
+                OsStr::new("--git-dir").to_owned(),
+                path.as_os_str().to_owned(),
+            ];
+            args.extend(
+                [
+                    "fetch",
+                    "--quiet",
+                    "--prune",
+                    "--no-tags",
+                    "--filter=blob:none",
+                    "origin",
+                ]
+                .iter()
+                .chain(MIRROR_REFSPECS.iter())
+                .map(|a| OsStr::new(a).to_owned()),
+            );
+            run_git(url, args)?;
+        } else {
+            let mut fetch_opts = FetchOptions::new();
+            fetch_opts.remote_callbacks(remote_callbacks());
+            fetch_opts.download_tags(AutotagOption::None);
+            fetch_opts.prune(git2::FetchPrune::On);
+            remote.fetch(&MIRROR_REFSPECS, Some(&mut fetch_opts), None)?;
+        }
+
+        debug!(
+            "Fetched {} byte(s) into {} mirror {} in {:?}",
+            dir_size(&path).saturating_sub(size_before),
+            if partial { "partial" } else { "full" },
+            path.display(),
+            started.elapsed()
+        );
+
+        // Mirror the remote's HEAD so the default branch can be resolved offline.
+        repo.set_head(&default_branch)?;
+    }
+
+    Ok(repo)
+}
+
+// Fetch the blobs under `subdir` of `commit` that a partial mirror doesn't have yet, in one
+// request, so they can be read with libgit2 afterwards. Full mirrors have everything already.
+#[tracing::instrument(skip(repo, commit))]
+pub fn prefetch_blobs(repo: &Repository, commit: &Commit, subdir: Option<&str>) -> Result<()> {
+    if !is_partial_mirror(repo) {
+        return Ok(());
+    }
+
+    let mut tree = commit.tree()?;
+    if let Some(subdir) = subdir
+        .map(|s| s.trim_matches('/'))
+        .filter(|s| !s.is_empty())
+    {
+        // A missing subdir is reported by whoever reads the files.
+        let Ok(entry) = tree.get_path(Path::new(subdir)) else {
+            return Ok(());
+        };
+        tree = entry.to_object(repo)?.peel_to_tree()?;
+    }
+
+    let odb = repo.odb()?;
+    let mut missing = Vec::new();
+    tree.walk(TreeWalkMode::PreOrder, |_, entry| {
+        if entry.kind() == Some(ObjectType::Blob) && !odb.exists(entry.id()) {
+            missing.push(entry.id().to_string());
+        }
+        TreeWalkResult::Ok
+    })?;
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let started = Instant::now();
+    let url = repo
+        .find_remote("origin")
+        .ok()
+        .and_then(|r| r.url().map(|u| u.to_owned()))
+        .unwrap_or_default();
+    // The same fetch git itself does for missing objects of partial clones.
+    let mut args = vec![
+        OsStr::new("--git-dir").to_owned(),
+        repo.path().as_os_str().to_owned(),
+    ];
+    args.extend(
+        [
+            "-c",
+            "fetch.negotiationAlgorithm=noop",
+            "fetch",
+            "--quiet",
+            "--no-tags",
+            "--no-write-fetch-head",
+            "--recurse-submodules=no",
+            "--filter=blob:none",
+            "origin",
+        ]
+        .iter()
+        .map(|a| OsStr::new(a).to_owned()),
+    );
+    args.extend(missing.iter().map(|oid| OsStr::new(oid).to_owned()));
+    run_git(&url, args)?;
+
+    debug!(
+        "Fetched {} missing blob(s) in {:?}",
+        missing.len(),
+        started.elapsed()
+    );
+    Ok(())
+}
+
+// Write the files of `commit` into `dest`, limited to `subdir` when given (a sparse checkout).
+#[tracing::instrument(skip(repo, commit))]
+pub fn checkout_commit(
+    repo: &Repository,
+    commit: &Commit,
+    dest: &Path,
+    subdir: Option<&str>,
+) -> Result<()> {
+    let subdir = subdir
+        .map(|s| s.trim_matches('/'))
+        .filter(|s| !s.is_empty());
+    let started = Instant::now();
+    fs::create_dir_all(dest)?;
+
+    if is_partial_mirror(repo) {
+        let sha = commit.id().to_string();
+        let git_dir = repo.path().as_os_str();
+        let args: Vec<&OsStr> = vec![
+            OsStr::new("--git-dir"),
+            git_dir,
+            OsStr::new("--work-tree"),
+            dest.as_os_str(),
+            OsStr::new("checkout"),
+            OsStr::new("--quiet"),
+            OsStr::new("--force"),
+            OsStr::new(&sha),
+            OsStr::new("--"),
+            OsStr::new(subdir.unwrap_or(".")),
+        ];
+        let url = repo
+            .find_remote("origin")
+            .ok()
+            .and_then(|r| r.url().map(|u| u.to_owned()))
+            .unwrap_or_default();
+        run_git(&url, args)?;
+    } else {
+        let mut checkout = CheckoutBuilder::new();
+        checkout
+            .target_dir(dest)
+            .update_index(false)
+            .recreate_missing(true)
+            .force();
+        if let Some(subdir) = subdir {
+            checkout.path(subdir);
+        }
+        repo.checkout_tree(commit.as_object(), Some(&mut checkout))?;
+    }
+
+    debug!(
+        "Checked out {} byte(s){} into {} in {:?}",
+        dir_size(dest),
+        subdir.map(|s| format!(" of `{s}`")).unwrap_or_default(),
+        dest.display(),
+        started.elapsed()
+    );
+
+    Ok(())
+}
+
+// Open an existing mirror without fetching.
+#[tracing::instrument]
+pub fn open_mirror(url: &str) -> Option<Repository> {
+    let path = get_mirror_path(url).ok()?;
+    Repository::open_bare(path).ok()
+}
+
+#[derive(Debug, Clone)]
+pub struct MirrorInfo {
+    pub key: String,
+    pub url: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub modified_at: Option<SystemTime>,
+}
+
+#[tracing::instrument]
+pub fn list_mirrors() -> Result<Vec<MirrorInfo>> {
+    let cache_dir = get_mirror_cache_dir()?;
+    if !cache_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut mirrors = Vec::new();
+    for entry in fs::read_dir(&cache_dir)? {
+        let path = entry?.path();
+        let Ok(repo) = Repository::open_bare(&path) else {
+            continue;
+        };
+        let url = repo
+            .find_remote("origin")
+            .ok()
+            .and_then(|r| r.url().map(|u| u.to_owned()))
+            .unwrap_or_default();
+        // FETCH_HEAD is rewritten on every fetch, so it doubles as "last fetched".
+        let modified_at = fs::metadata(path.join("FETCH_HEAD"))
+            .or_else(|_| fs::metadata(&path))
+            .and_then(|m| m.modified())
+            .ok();
+        mirrors.push(MirrorInfo {
+            key: path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            url,
+            size_bytes: dir_size(&path),
+            modified_at,
+            path,
+        });
+    }
+    mirrors.sort_by(|a, b| a.key.cmp(&b.key));
+
+    Ok(mirrors)
+}
+
+#[tracing::instrument]
+pub fn remove_mirror(mirror: &MirrorInfo) -> Result<()> {
+    let _lock = lock_mirror_file(&mirror.path)?;
+    fs::remove_dir_all(&mirror.path)?;
+    Ok(())
+}
+
+fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_repo_url_forms_of_the_same_repo() {
+        for url in [
+            "https://github.com/Org/Repo",
+            "https://github.com/Org/Repo.git",
+            "https://GitHub.com/Org/Repo/",
+            "https://user@github.com/Org/Repo.git",
+            "ssh://git@github.com/Org/Repo.git",
+            "git@github.com:Org/Repo.git",
+            "git@GITHUB.COM:Org/Repo",
+        ] {
+            assert_eq!(normalize_repo_url(url), "github.com/Org/Repo", "{url}");
+        }
+    }
+
+    #[test]
+    fn normalize_repo_url_keeps_path_case() {
+        assert_ne!(
+            normalize_repo_url("https://github.com/Org/Repo"),
+            normalize_repo_url("https://github.com/org/repo")
+        );
+        assert_ne!(
+            normalize_repo_url("git@github.com:Org/Repo"),
+            normalize_repo_url("git@github.com:org/repo")
+        );
+    }
+
+    #[test]
+    fn normalize_repo_url_local_paths() {
+        assert_eq!(normalize_repo_url("file:///tmp/Repos/a"), "/tmp/Repos/a");
+        assert_eq!(normalize_repo_url("file:///tmp/Repos/a/"), "/tmp/Repos/a");
+        assert_eq!(normalize_repo_url("/tmp/Repos/a.git"), "/tmp/Repos/a");
+        assert_eq!(normalize_repo_url("./Repos/a"), "./Repos/a");
+        assert_ne!(
+            normalize_repo_url("file:///tmp/Repos/a"),
+            normalize_repo_url("file:///tmp/repos/a")
+        );
+    }
+
+    #[test]
+    fn mirror_key_is_stable_and_case_distinct() {
+        assert_eq!(
+            mirror_key("https://github.com/Org/Repo.git"),
+            mirror_key("git@github.com:Org/Repo")
+        );
+        assert_ne!(
+            mirror_key("https://github.com/Org/Repo"),
+            mirror_key("https://github.com/org/repo")
+        );
+        assert_ne!(mirror_key("/tmp/Repos/a"), mirror_key("/tmp/repos/a"));
+
+        let key = mirror_key("git@github.com:Org/Repo");
+        assert!(key.starts_with("github.com_Org_Repo-"), "{key}");
+        assert!(
+            key.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-._".contains(c))
+        );
+    }
+}
@@ -1,4 +1,10 @@
+pub mod archive;
+pub mod auth;
+pub mod changelog;
+pub mod fs;
 pub mod lib;
+pub mod mirror;
+pub mod pack;
 pub mod static_analysis;
 
 pub use lib::*;
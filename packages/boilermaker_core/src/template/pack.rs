@@ -0,0 +1,373 @@
+use std::fs::{self, File};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use color_eyre::{Result, eyre::eyre};
+use flate2::{Compression, write::GzEncoder};
+use git2::{Repository, StatusOptions};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+use super::archive::ArchiveKind;
+use super::fs::{
+    DirFs, MemoryFs, TemplateFs, read_template_config, sha256_hash_fs, template_langs,
+};
+use super::static_analysis::find_variables_in_fs;
+use crate::config::get_template_config;
+use crate::util::crypto::sha256_hash_dir;
+
+// Packed archives hold the manifest next to a `template/` dir with the template files, so
+// installing a pack gives back exactly the files that were packed.
+pub const PACK_MANIFEST_FILE: &str = "boilermaker-manifest.toml";
+pub const PACK_TEMPLATE_DIR: &str = "template";
+const PACK_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackManifest {
+    pub format_version: u32,
+    pub name: String,
+    pub version: String,
+    // sha256 of the template files, as computed by `sha256_hash_dir`.
+    pub checksum: String,
+    pub commit: Option<String>,
+    pub packed_at: String,
+    // The `[project]` table of `boilermaker.toml`, as is.
+    pub project: toml::Table,
+}
+
+#[derive(Debug)]
+pub struct PackedTemplate {
+    pub path: PathBuf,
+    pub manifest: PackManifest,
+    pub files: usize,
+}
+
+// Check that `dir` is a template `boil install` and `boil new` can use. Problems that would break
+// installs are errors, the rest are logged as warnings.
+#[tracing::instrument]
+pub fn validate_template(dir: &Path) -> Result<()> {
+    let template_fs = DirFs::new(dir);
+    let config = read_template_config(&template_fs)?;
+
+    if config.project.name.as_deref().unwrap_or("").is_empty() {
+        return Err(eyre!("💥 Add `name` to [project] in boilermaker.toml."));
+    }
+    if config.project.version.as_deref().unwrap_or("").is_empty() {
+        return Err(eyre!("💥 Add `version` to [project] in boilermaker.toml."));
+    }
+    // Both end up in the archive's file name.
+    check_file_name_part("name", config.project.name.as_deref().unwrap_or(""))?;
+    check_file_name_part("version", config.project.version.as_deref().unwrap_or(""))?;
+
+    let files = template_fs.list_files()?;
+    let langs = template_langs(&template_fs)?;
+    if langs.is_empty() {
+        return Err(eyre!(
+            "💥 No language directories found in {}.",
+            dir.display()
+        ));
+    }
+    if let Some(lang) = &config.project.default_lang
+        && !langs.contains(lang)
+    {
+        return Err(eyre!(
+            "💥 `default_lang` is `{lang}` but there's no `{lang}` directory."
+        ));
+    }
+
+    // `boil new` renders every text file, so each one has to be valid Jinja.
+    let jinja = minijinja::Environment::new();
+    for file in files
+        .iter()
+        .filter(|f| langs.iter().any(|l| f.starts_with(l)))
+    {
+        let Some(content) = template_fs.read_text(file)? else {
+            continue;
+        };
+        if let Err(e) = jinja.template_from_str(&content) {
+            return Err(eyre!(
+                "💥 Invalid template syntax in {}: {e}",
+                file.display()
+            ));
+        }
+    }
+
+    let declared = config
+        .variables
+        .as_ref()
+        .map(|vars| vars.as_map().keys().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+    let mut undeclared = Vec::new();
+    for lang in &langs {
+        for var in find_variables_in_fs(&template_fs, Some(Path::new(lang)))? {
+            if !declared.contains(&var) && !undeclared.contains(&var) {
+                undeclared.push(var);
+            }
+        }
+    }
+    undeclared.sort();
+    if !undeclared.is_empty() {
+        warn!(
+            "Variables used but not declared in [variables]: {}",
+            undeclared.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+// Validate the template in `dir` and write it to `<name>-<version>.<ext>` in `out_dir`.
+#[tracing::instrument]
+pub fn pack_template(dir: &Path, out_dir: &Path, kind: ArchiveKind) -> Result<PackedTemplate> {
+    validate_template(dir)?;
+
+    let config = get_template_config(dir)?;
+    let name = config.project.name.unwrap_or_default();
+    let version = config.project.version.unwrap_or_default();
+    let raw_config: toml::Table =
+        toml::from_str(&fs::read_to_string(dir.join("boilermaker.toml"))?)?;
+    let project = raw_config
+        .get("project")
+        .and_then(|p| p.as_table())
+        .cloned()
+        .unwrap_or_default();
+
+    let manifest = PackManifest {
+        format_version: PACK_FORMAT_VERSION,
+        name: name.to_owned(),
+        version: version.to_owned(),
+        checksum: sha256_hash_dir(dir)?,
+        commit: find_source_commit(dir),
+        packed_at: Utc::now().to_rfc3339(),
+        project,
+    };
+    let manifest_toml = toml::to_string_pretty(&manifest)?;
+
+    let files = list_pack_files(dir)?;
+    fs::create_dir_all(out_dir)?;
+    let ext = match kind {
+        ArchiveKind::TarGz => "tar.gz",
+        ArchiveKind::Zip => "zip",
+    };
+    let path = out_dir.join(format!("{name}-{version}.{ext}"));
+
+    match kind {
+        ArchiveKind::TarGz => write_tar_gz(&path, &files, &manifest_toml)?,
+        ArchiveKind::Zip => write_zip(&path, &files, &manifest_toml)?,
+    }
+
+    Ok(PackedTemplate {
+        path,
+        manifest,
+        files: files.iter().filter(|f| f.is_file).count(),
+    })
+}
+
+// Only letters, digits, `-`, `_`, `+` and `.`, and no leading `.`, so the value can't point a path
+// outside the dir it's joined to.
+pub fn is_file_name_safe(value: &str) -> bool {
+    !value.is_empty()
+        && !value.starts_with('.')
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '+' | '.'))
+}
+
+fn check_file_name_part(field: &str, value: &str) -> Result<()> {
+    if !is_file_name_safe(value) {
+        return Err(eyre!(
+            "💥 `{field}` is used in the archive name, so it can only have letters, digits, `-`, `_`, \
+             `+` and `.`: `{value}`."
+        ));
+    }
+    Ok(())
+}
+
+// Read and verify the manifest of a pack read with `read_archive`. Returns `None` for archives that
+// weren't made by `boil template pack`.
+#[tracing::instrument]
+pub fn read_pack_manifest(archive: &MemoryFs) -> Result<Option<PackManifest>> {
+    let manifest_path = Path::new(PACK_MANIFEST_FILE);
+    if !archive.exists(manifest_path) {
+        return Ok(None);
+    }
+
+    let manifest_toml = archive
+        .read_text(manifest_path)?
+        .ok_or_else(|| eyre!("💥 {PACK_MANIFEST_FILE} is not valid UTF-8"))?;
+    let manifest: PackManifest = toml::from_str(&manifest_toml)?;
+    if manifest.format_version > PACK_FORMAT_VERSION {
+        return Err(eyre!(
+            "💥 Template pack format {} is newer than this version of boil supports.",
+            manifest.format_version
+        ));
+    }
+
+    let checksum = sha256_hash_fs(&archive.subdir(Path::new(PACK_TEMPLATE_DIR)))?;
+    if checksum != manifest.checksum {
+        return Err(eyre!(
+            "💥 Checksum mismatch for {} {}: the archive is corrupt or was modified.",
+            manifest.name,
+            manifest.version
+        ));
+    }
+    info!(
+        "Verified template pack {} {} ({})",
+        manifest.name,
+        manifest.version,
+        &manifest.checksum[..12]
+    );
+
+    Ok(Some(manifest))
+}
+
+// The HEAD commit of the repo `dir` is in, if any. Packing uncommitted changes is allowed, but
+// then the commit doesn't describe the packed files, so warn about it.
+fn find_source_commit(dir: &Path) -> Option<String> {
+    let repo = Repository::discover(dir).ok()?;
+    let commit = repo.head().ok()?.peel_to_commit().ok()?.id().to_string();
+    if has_uncommitted_changes(&repo, dir) {
+        warn!("Packing uncommitted changes, the recorded commit won't match the packed files.");
+    }
+    Some(commit)
+}
+
+fn has_uncommitted_changes(repo: &Repository, dir: &Path) -> bool {
+    let (Some(workdir), Ok(dir)) = (repo.workdir(), dir.canonicalize()) else {
+        return false;
+    };
+    let Ok(workdir) = workdir.canonicalize() else {
+        return false;
+    };
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true);
+    if let Ok(rel) = dir.strip_prefix(&workdir)
+        && !rel.as_os_str().is_empty()
+    {
+        opts.pathspec(rel);
+    }
+    repo.statuses(Some(&mut opts)).is_ok_and(|s| !s.is_empty())
+}
+
+#[derive(Debug)]
+struct PackFile {
+    rel: String,
+    abs: PathBuf,
+    is_file: bool,
+    executable: bool,
+}
+
+// Everything under `dir` except .git, in sorted order so packing the same tree twice gives the
+// same archive contents. Symlinks are skipped, like on install.
+fn list_pack_files(dir: &Path) -> Result<Vec<PackFile>> {
+    let mut files = Vec::new();
+    let entries = WalkDir::new(dir)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git");
+    for entry in entries {
+        let entry = entry?;
+        let file_type = entry.file_type();
+        if !file_type.is_file() && !file_type.is_dir() {
+            warn!("Skipping symlink: {}", entry.path().display());
+            continue;
+        }
+        let rel = entry
+            .path()
+            .strip_prefix(dir)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        files.push(PackFile {
+            rel: format!("{PACK_TEMPLATE_DIR}/{rel}"),
+            abs: entry.path().to_path_buf(),
+            is_file: file_type.is_file(),
+            executable: is_executable(entry.path()),
+        });
+    }
+    Ok(files)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    false
+}
+
+fn file_mode(file: &PackFile) -> u32 {
+    if !file.is_file || file.executable {
+        0o755
+    } else {
+        0o644
+    }
+}
+
+// Timestamps and owners are zeroed so archives only differ when the files do.
+fn write_tar_gz(path: &Path, files: &[PackFile], manifest: &str) -> Result<()> {
+    let encoder = GzEncoder::new(File::create(path)?, Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    let mut append = |name: &str, entry_type: tar::EntryType, mode: u32, data: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_size(data.len() as u64);
+        header.set_mode(mode);
+        header.set_mtime(0);
+        header.set_uid(0);
+        header.set_gid(0);
+        builder.append_data(&mut header, name, data)
+    };
+
+    append(
+        PACK_MANIFEST_FILE,
+        tar::EntryType::Regular,
+        0o644,
+        manifest.as_bytes(),
+    )?;
+    append(PACK_TEMPLATE_DIR, tar::EntryType::Directory, 0o755, &[])?;
+    for file in files {
+        if file.is_file {
+            let data = fs::read(&file.abs)?;
+            append(&file.rel, tar::EntryType::Regular, file_mode(file), &data)?;
+        } else {
+            append(&file.rel, tar::EntryType::Directory, file_mode(file), &[])?;
+        }
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+fn write_zip(path: &Path, files: &[PackFile], manifest: &str) -> Result<()> {
+    let mut zip = zip::ZipWriter::new(File::create(path)?);
+    let options = |mode: u32| {
+        zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .last_modified_time(zip::DateTime::default())
+            .unix_permissions(mode)
+    };
+
+    zip.start_file(PACK_MANIFEST_FILE, options(0o644))?;
+    zip.write_all(manifest.as_bytes())?;
+    zip.add_directory(PACK_TEMPLATE_DIR, options(0o755))?;
+    for file in files {
+        if file.is_file {
+            zip.start_file(file.rel.as_str(), options(file_mode(file)))?;
+            zip.write_all(&fs::read(&file.abs)?)?;
+        } else {
+            zip.add_directory(file.rel.as_str(), options(file_mode(file)))?;
+        }
+    }
+
+    zip.finish()?;
+    Ok(())
+}
@@ -1,11 +1,10 @@
 use std::collections::HashSet;
-use std::fs::File;
-use std::io::Read as _;
 use std::path::Path;
 
-use color_eyre::eyre::{Result, eyre};
+use color_eyre::eyre::Result;
 use regex::Regex;
-use walkdir::WalkDir;
+
+use super::fs::{DirFs, TemplateFs};
 
 // An alpha-numeric string enclosed in {{ }}.
 const JINJA_VAR_REGEX: &str = r"\{\{\s*([\w_-]+)\s*\}\}";
@@ -13,34 +12,35 @@ const JINJA_VAR_REGEX: &str = r"\{\{\s*([\w_-]+)\s*\}\}";
 // Find all template variables in files under `root`.
 #[tracing::instrument]
 pub fn find_variables_in_path(root: &str) -> Result<HashSet<String>> {
+    find_variables_in_fs(&DirFs::new(root), None)
+}
+
+// Find all template variables in the files of `template_fs`, or only those under `dir`.
+#[tracing::instrument]
+pub fn find_variables_in_fs(
+    template_fs: &dyn TemplateFs,
+    dir: Option<&Path>,
+) -> Result<HashSet<String>> {
     let mut vars: HashSet<String> = HashSet::new();
     let re = Regex::new(JINJA_VAR_REGEX).unwrap();
 
-    for entry in WalkDir::new(root) {
-        match entry {
-            Ok(entry) => {
-                if entry.file_type().is_dir() {
-                    continue;
-                }
-
-                let file_vars = find_vars_in_file(&re, entry.path())?;
-                for name in file_vars {
-                    vars.insert(name);
-                }
-            }
-            Err(e) => return Err(eyre!("Error walking {}: {}", root, e)),
+    for path in template_fs.list_files()? {
+        if dir.is_some_and(|dir| !path.starts_with(dir)) {
+            continue;
+        }
+        // Binary files aren't rendered, so they can't use variables.
+        let Some(contents) = template_fs.read_text(&path)? else {
+            continue;
+        };
+        for name in find_vars_in_str(&re, &contents) {
+            vars.insert(name);
         }
     }
     Ok(vars)
 }
 
-fn find_vars_in_file(re: &Regex, path: &Path) -> Result<Vec<String>> {
-    let mut file = File::open(path)?;
-    let mut contents = String::new();
-
-    file.read_to_string(&mut contents)?;
-    let vars: Vec<String> = re
-        .captures_iter(&contents)
+fn find_vars_in_str(re: &Regex, contents: &str) -> Vec<String> {
+    re.captures_iter(contents)
         .flat_map(|captures| {
             captures
                 .iter()
@@ -49,7 +49,5 @@ fn find_vars_in_file(re: &Regex, path: &Path) -> Result<Vec<String>> {
                 .map(|cap| cap.as_str().to_string())
                 .collect::<Vec<_>>()
         })
-        .collect();
-
-    Ok(vars)
+        .collect()
 }
@@ -1,7 +1,37 @@
+use std::fs;
+use std::path::Path;
+
+use color_eyre::Result;
 use hex;
 use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
 
 pub fn sha256_hash_string(s: &str) -> String {
     let result = Sha256::digest(s.as_bytes());
     hex::encode(result)
 }
+
+// Hash every file under `dir` (relative path + content), ignoring any .git dirs. Files are walked
+// in sorted order so the same tree always gives the same hash.
+#[tracing::instrument]
+pub fn sha256_hash_dir(dir: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let entries = WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git");
+
+    for entry in entries {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let rel_path = entry.path().strip_prefix(dir)?;
+        hasher.update(rel_path.to_string_lossy().as_bytes());
+        hasher.update([0u8]);
+        hasher.update(fs::read(entry.path())?);
+        hasher.update([0u8]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
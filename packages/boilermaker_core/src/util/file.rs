@@ -1,4 +1,7 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 use color_eyre::{Result, eyre::eyre};
 use fs_extra::{copy_items, dir::CopyOptions};
@@ -10,6 +13,12 @@ pub fn read_file_to_string(path: &PathBuf) -> Result<String> {
     Ok(content)
 }
 
+// Read a file as text. Returns `None` for binary (non UTF-8) files.
+#[tracing::instrument]
+pub fn read_text_file(path: &Path) -> Result<Option<String>> {
+    Ok(String::from_utf8(fs::read(path)?).ok())
+}
+
 #[tracing::instrument]
 pub fn remove_git_dir(dir: &PathBuf) -> Result<()> {
     let git_dir = dir.join(".git");
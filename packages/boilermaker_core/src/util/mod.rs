@@ -3,5 +3,6 @@ pub mod env;
 pub mod file;
 pub mod fmt;
 pub mod markdown;
+pub mod output;
 pub mod string;
 pub mod time;
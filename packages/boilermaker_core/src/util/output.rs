@@ -0,0 +1,119 @@
+use clap::ValueEnum;
+use color_eyre::{Result, eyre::eyre};
+use serde::Serialize;
+use serde_json::Value;
+
+// How commands print their results. `table` is for people, the other formats serialize the result
+// structs to stdout for scripts. Logs always go to stderr, so they never mix with the output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Yaml,
+    Csv,
+}
+
+impl OutputFormat {
+    pub fn is_table(self) -> bool {
+        self == OutputFormat::Table
+    }
+}
+
+// Print `value` to stdout as JSON, YAML or CSV. Tables are printed by each command itself.
+pub fn print_serialized<T: Serialize + ?Sized>(format: OutputFormat, value: &T) -> Result<()> {
+    print!("{}", serialize(format, value)?);
+    Ok(())
+}
+
+pub fn serialize<T: Serialize + ?Sized>(format: OutputFormat, value: &T) -> Result<String> {
+    let value = serde_json::to_value(value)?;
+    match format {
+        OutputFormat::Table => Err(eyre!("💥 Tables can't be serialized.")),
+        OutputFormat::Json => Ok(format!("{}\n", serde_json::to_string_pretty(&value)?)),
+        OutputFormat::Yaml => Ok(serde_yaml_ng::to_string(&value)?),
+        OutputFormat::Csv => to_csv(&value),
+    }
+}
+
+// One row per item, with a header of every field. Nested lists and objects are written as JSON.
+fn to_csv(value: &Value) -> Result<String> {
+    let rows = match value {
+        Value::Array(items) => items.iter().collect::<Vec<_>>(),
+        Value::Object(_) => vec![value],
+        _ => return Err(eyre!("💥 Only lists and records can be written as CSV.")),
+    };
+
+    let mut header: Vec<&String> = Vec::new();
+    for row in &rows {
+        let Value::Object(map) = row else {
+            return Err(eyre!("💥 Only lists of records can be written as CSV."));
+        };
+        for key in map.keys() {
+            if !header.contains(&key) {
+                header.push(key);
+            }
+        }
+    }
+
+    if header.is_empty() {
+        return Ok(String::new());
+    }
+
+    let mut out = String::new();
+    let line = |cells: Vec<String>| format!("{}\n", cells.join(","));
+    out.push_str(&line(header.iter().map(|k| csv_cell(k)).collect()));
+    for row in &rows {
+        let cells = header
+            .iter()
+            .map(|key| match row.get(key.as_str()) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(s)) => csv_cell(s),
+                Some(other) => csv_cell(&other.to_string()),
+            })
+            .collect();
+        out.push_str(&line(cells));
+    }
+    Ok(out)
+}
+
+fn csv_cell(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) || s.trim() != s {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn yaml_round_trips() {
+        let value = json!([
+            {"name": "yes", "version": "1.0", "tags": ["a: b", "- c"], "note": null},
+            {"name": "multi\nline", "version": 2, "tags": [], "note": "#hash"},
+        ]);
+        let yaml = serialize(OutputFormat::Yaml, &value).unwrap();
+        let parsed: Value = serde_yaml_ng::from_str(&yaml).unwrap();
+        assert_eq!(parsed, value);
+    }
+
+    #[test]
+    fn csv_rows_and_quoting() {
+        let value = json!([
+            {"id": 1, "name": "a,b", "tags": ["x"]},
+            {"id": 2, "name": "say \"hi\"", "extra": " padded"},
+        ]);
+        let csv = serialize(OutputFormat::Csv, &value).unwrap();
+        assert_eq!(
+            csv,
+            "id,name,tags,extra\n1,\"a,b\",\"[\"\"x\"\"]\",\n2,\"say \"\"hi\"\"\",,\" padded\"\n"
+        );
+        assert!(serialize(OutputFormat::Csv, &json!("text")).is_err());
+        assert!(serialize(OutputFormat::Table, &value).is_err());
+    }
+}
@@ -1,5 +1,5 @@
-use chrono::{DateTime, Utc};
-use color_eyre::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use color_eyre::{Result, eyre::eyre};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn current_timestamp_millis() -> Result<i32> {
@@ -21,3 +21,16 @@ pub fn timestamp_to_iso8601(timestamp: i64) -> String {
         DateTime::<Utc>::from(UNIX_EPOCH + std::time::Duration::from_secs(timestamp as u64));
     datetime.to_rfc3339()
 }
+
+// A date (`2025-01-31`, midnight UTC) or an RFC 3339 timestamp, as seconds since the epoch.
+pub fn parse_timestamp(value: &str) -> Result<i64> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.timestamp());
+    }
+    match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) => Ok(date.and_time(Default::default()).and_utc().timestamp()),
+        Err(_) => Err(eyre!(
+            "💥 Invalid date `{value}`, expected YYYY-MM-DD or an RFC 3339 timestamp."
+        )),
+    }
+}
@@ -5,8 +5,10 @@ use once_cell::sync::OnceCell;
 
 use boilermaker_core::{
     config::{get_system_config, DEFAULT_LOCAL_CACHE_PATH_STRING},
-    db::{LocalCache, TemplateDb, TemplateMethods},
+    db::{LocalCache, TemplateDb},
     state::AppState,
+    template::auth::init_git_auth,
+    util::output::OutputFormat,
 };
 
 pub static APP_STATE: OnceCell<AppState> = OnceCell::new();
@@ -26,18 +28,19 @@ pub fn init_app_state() -> Result<()> {
                     err
                 )
             })?);
-            if !cache.template_table_exists().await.unwrap_or(false) {
-                cache
-                    .create_schema()
-                    .await
-                    .map_err(|e| eyre!("Failed to initialize local cache: {}", e))?;
-            }
+            // Migrations are idempotent, so always run them to bring older caches up to date.
+            cache
+                .create_schema()
+                .await
+                .map_err(|e| eyre!("Failed to initialize local cache: {}", e))?;
             // App state
             let sys_config = get_system_config(None).expect("Failed to load system config");
+            init_git_auth(sys_config.git.as_ref());
             let app_state = AppState {
                 local_db: cache,
                 sys_config,
                 log_level: 1,
+                output_format: OutputFormat::Table,
             };
             APP_STATE
                 .set(app_state)
@@ -2,13 +2,19 @@ use dioxus::prelude::*;
 use tracing::error;
 
 use crate::APP_STATE;
-use boilermaker_core::db::{ListTemplateOptions, TemplateResult};
+use boilermaker_core::commands::search::{search_templates, SearchScope};
+use boilermaker_core::db::{ListTemplateOptions, SearchQuery, TemplateResult, TemplateSort};
 
-// This handles a list of templates as a Dioxus context. Currently, loads all templates from DB, but
-// eventually it could be extended to load templates from sources & search.
+// This handles a list of templates as a Dioxus context. Loads the latest templates from DB, or the
+// installed templates matching `query` (same syntax as `boil search`), narrowed to `tag` if one is
+// picked.
 #[derive(Clone, Copy)]
 pub struct TemplatesContext {
     pub templates: Signal<Vec<TemplateResult>>,
+    pub query: Signal<String>,
+    pub tag: Signal<Option<String>>,
+    // Every tag of the installed templates, for the filter chips.
+    pub tags: Signal<Vec<String>>,
 
     // Used in refresh() to trigger, well yes, a refresh.
     refresh_trigger: Signal<usize>,
@@ -23,18 +29,33 @@ impl TemplatesContext {
     pub fn is_empty(&self) -> bool {
         self.templates.read().is_empty()
     }
+
+    // Filter on `tag`, or stop filtering when it's the current one.
+    pub fn toggle_tag(&mut self, tag: &str) {
+        let current = self.tag.read().clone();
+        if current.as_deref() == Some(tag) {
+            self.tag.set(None);
+        } else {
+            self.tag.set(Some(tag.to_owned()));
+        }
+    }
 }
 
 // Initialize once at the root (in App).
 pub fn init_templates_context() {
     let templates = use_signal::<Vec<TemplateResult>>(Vec::new);
+    let query = use_signal(String::new);
+    let tag = use_signal::<Option<String>>(|| None);
+    let tags = use_signal::<Vec<String>>(Vec::new);
     let refresh_trigger = use_signal(|| 0usize);
 
     use_resource(move || async move {
         let trigger = refresh_trigger.read();
         // Drop immediately to avoid holding the read-references over an await call.
         drop(trigger);
-        if let Err(e) = load_templates(templates).await {
+        let query = query.read().clone();
+        let tag = tag.read().clone();
+        if let Err(e) = load_templates(templates, tags, &query, tag).await {
             error!("Error loading templates: {}", e);
         }
     });
@@ -42,18 +63,51 @@ pub fn init_templates_context() {
     // Shares the context to all child elements.
     use_context_provider(|| TemplatesContext {
         templates,
+        query,
+        tag,
+        tags,
         refresh_trigger,
     });
 }
 
 async fn load_templates(
     mut templates: Signal<Vec<TemplateResult>>,
+    mut tags: Signal<Vec<String>>,
+    query: &str,
+    tag: Option<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let cache = &APP_STATE.get().ok_or("APP_STATE not initialized")?.local_db;
+
+    let all_tags = cache.list_tags().await?;
+    tags.set(
+        all_tags
+            .into_iter()
+            .filter(|t| t.templates > 0)
+            .map(|t| t.name)
+            .collect(),
+    );
+
+    if !query.trim().is_empty() {
+        let mut query = SearchQuery::parse(query)?;
+        query.tags.extend(tag);
+        let results = search_templates(cache.clone(), &query, SearchScope::Local).await?;
+        let ids = results.iter().map(|r| r.id).collect::<Vec<_>>();
+        let list_opts = Some(ListTemplateOptions {
+            ids: Some(ids.clone()),
+            ..Default::default()
+        });
+        // One query for every hit, then back in search rank order.
+        let mut rows = cache.list_templates(list_opts).await?;
+        rows.sort_by_key(|row| ids.iter().position(|id| *id == row.id));
+        templates.set(rows);
+        return Ok(());
+    }
+
     let list_opts = Some(ListTemplateOptions {
-        order_by: Some("created_at DESC, name ASC".to_string()),
+        sort: TemplateSort::Newest,
         limit: Some(10),
-        offset: None,
+        tags: tag.into_iter().collect(),
+        ..Default::default()
     });
 
     let rows = cache.list_templates(list_opts).await?;
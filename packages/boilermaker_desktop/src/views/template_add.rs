@@ -4,6 +4,8 @@ use dioxus::prelude::*;
 
 use boilermaker_core::commands::install::{install, Install};
 use boilermaker_core::constants::{BRANCH_PATTERN, SUBDIR_PATTERN};
+use boilermaker_core::db::{normalize_tags, Visibility};
+use boilermaker_core::template::auth::remote_callbacks;
 use boilermaker_core::util::string::string_to_option;
 use boilermaker_desktop::TemplatesContext;
 use boilermaker_desktop::APP_STATE;
@@ -26,7 +28,9 @@ pub fn TemplateAdd() -> Element {
     let mut subdir = use_signal(String::new);
     let mut lang = use_signal(String::new);
     let mut name = use_signal(String::new);
+    let mut visibility = use_signal(String::new);
     let mut description = use_signal(String::new);
+    let mut tags = use_signal(String::new);
     let mut status = use_signal(StatusHashMapType::new);
     let mut processing = use_signal(|| false);
     let mut result_message = use_signal(|| ResultMessage::None);
@@ -75,8 +79,11 @@ pub fn TemplateAdd() -> Element {
                                     template: sigval(&template),
                                     branch: string_to_option(&sigval(&branch)),
                                     subdir: string_to_option(&sigval(&subdir)),
-                                    lang: string_to_option(&sigval(&lang)),
+                                    lang: string_to_option(&sigval(&lang)).into_iter().collect(),
                                     name: string_to_option(&sigval(&name)),
+                                    visibility: visibility_option(&sigval(&visibility)),
+                                    description: string_to_option(&sigval(&description)),
+                                    tags: sigval(&tags).split(',').map(str::to_owned).collect(),
                                 };
                                 match install(app_state, &data).await {
                                     Ok(_) => {
@@ -171,6 +178,24 @@ pub fn TemplateAdd() -> Element {
                                     onblur: move |e| validate_name(e, &name, &mut status),
                                 }
                             }
+                            div { class: "mb-4",
+                                label { class: LABEL_STYLE,
+                                    i { class: "fa-solid fa-eye" }
+                                    span { class: "ml-2", "Visibility (optional)" }
+                                }
+                                select {
+                                    name: "visibility",
+                                    class: INPUT_STYLE,
+                                    onchange: move |e| {
+                                        visibility.set(e.value());
+                                        validate_visibility(&template, &visibility, &mut status);
+                                    },
+                                    value: "{visibility}",
+                                    option { value: "", "Default (private for local paths and private hosts)" }
+                                    option { value: "public", "Public" }
+                                    option { value: "private", "Private" }
+                                }
+                            }
                             div { class: "mb-4",
                                 label { class: LABEL_STYLE,
                                     i { class: "fa-solid fa-file-lines" }
@@ -185,6 +210,21 @@ pub fn TemplateAdd() -> Element {
                                     onblur: move |e| validate_description(e, &description, &mut status),
                                 }
                             }
+                            div { class: "mb-4",
+                                label { class: LABEL_STYLE,
+                                    i { class: "fa-solid fa-tags" }
+                                    span { class: "ml-2", "Tags (optional)" }
+                                }
+                                input {
+                                    name: "tags",
+                                    r#type: "text",
+                                    class: INPUT_STYLE,
+                                    placeholder: "e.g. backend, cli",
+                                    oninput: move |e| tags.set(e.value()),
+                                    value: "{tags}",
+                                    onblur: move |e| validate_tags(e, &tags, &mut status),
+                                }
+                            }
                             div { class: "mb-6",
                                 button { class: BTN_GREEN_STYLE, r#type: "submit", "Add Template" }
                             }
@@ -209,7 +249,9 @@ fn AddTemplateStatusSidebar(status: StatusSignalType) -> Element {
         ("Subdirectory", "subdir",      "fa-solid fa-folder"),
         ("Lang",         "lang",        "fa-solid fa-language"),
         ("Name",         "name",        "fa-solid fa-signature"),
+        ("Visibility",   "visibility",  "fa-solid fa-eye"),
         ("Description",  "description", "fa-solid fa-file-lines"),
+        ("Tags",         "tags",        "fa-solid fa-tags"),
     ];
 
     rsx! {
@@ -259,6 +301,26 @@ pub fn validate_name(
     set_status(status, "name", true, "is valid");
 }
 
+// Empty leaves it to `install`, which infers it from the repo.
+fn visibility_option(value: &str) -> Option<Visibility> {
+    match value {
+        "public" => Some(Visibility::Public),
+        "private" => Some(Visibility::Private),
+        _ => None,
+    }
+}
+
+pub fn validate_visibility(
+    template: &SignalStringType,
+    signal: &SignalStringType,
+    status: &mut StatusSignalType,
+) {
+    let visibility = visibility_option(&sigval(signal))
+        .map(|v| v.to_string())
+        .unwrap_or_else(|| format!("{} (default)", Visibility::infer(&sigval(template))));
+    set_status(status, "visibility", true, &visibility);
+}
+
 pub fn validate_lang(
     _event: Event<FocusData>,
     _signal: &SignalStringType,
@@ -286,8 +348,9 @@ pub fn validate_template(
         return;
     }
 
+    // Private repos need the same credentials `install` uses.
     let mut remote = remote.unwrap();
-    match remote.connect(git2::Direction::Fetch) {
+    match remote.connect_auth(git2::Direction::Fetch, Some(remote_callbacks()), None) {
         Ok(_) => {}
         Err(e) => {
             set_status(status, "template", false, &format!("Invalid repo URL: {e}"));
@@ -331,3 +394,16 @@ pub fn validate_description(
 ) {
     set_status(status, "description", true, "is valid");
 }
+
+pub fn validate_tags(
+    _event: Event<FocusData>,
+    signal: &SignalStringType,
+    status: &mut StatusSignalType,
+) {
+    let tags = normalize_tags(&sigval(signal).split(',').collect::<Vec<_>>());
+    if tags.is_empty() {
+        set_status(status, "tags", true, "none");
+    } else {
+        set_status(status, "tags", true, &tags.join(", "));
+    }
+}
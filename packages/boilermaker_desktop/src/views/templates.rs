@@ -3,15 +3,23 @@ use dioxus::prelude::*;
 use crate::Route;
 use boilermaker_desktop::TemplatesContext;
 use boilermaker_ui::{
-    BTN_BLUE_STYLE, BTN_GREEN_STYLE, BTN_RED_STYLE, LINK_STYLE, TD_STYLE, TH_MUTED_STYLE, TH_STYLE,
+    BTN_BLUE_STYLE, BTN_GREEN_STYLE, BTN_RED_STYLE, CHIP_ACTIVE_STYLE, CHIP_STYLE, LINK_STYLE,
+    SEARCH_INPUT_STYLE, TD_STYLE, TH_MUTED_STYLE, TH_STYLE,
 };
 
 #[component]
 pub fn Templates() -> Element {
     // Get pre-loaded templates from context.
     let templates_ctx = use_context::<TemplatesContext>();
+    let mut query = templates_ctx.query;
+    let tag = templates_ctx.tag.read().clone();
+    let tags = templates_ctx.tags.read().clone();
     let templates = templates_ctx.templates.read();
-    let content = if templates.is_empty() {
+    let content = if templates.is_empty() && (!query.read().trim().is_empty() || tag.is_some()) {
+        rsx! {
+            div { class: "py-4 text-neutral-500 dark:text-neutral-200", "No templates match your search." }
+        }
+    } else if templates.is_empty() {
         rsx! {
             div { class: "py-4 text-neutral-500 dark:text-neutral-200",
                 "No templates found. "
@@ -27,6 +35,11 @@ pub fn Templates() -> Element {
                         th {}
                         th { class: TH_STYLE, "Name" }
                         th { class: TH_STYLE, "Language" }
+                        th { class: TH_STYLE, "Version" }
+                        th { class: TH_STYLE, "Description" }
+                        th { class: TH_STYLE, "Keywords" }
+                        th { class: TH_STYLE, "Tags" }
+                        th { class: TH_STYLE, "Visibility" }
                         th { class: TH_STYLE, "Repo" }
                         th { class: TH_STYLE, "Subdirectory" }
                         th { class: TH_MUTED_STYLE, "Actions" }
@@ -38,6 +51,11 @@ pub fn Templates() -> Element {
                             td { class: "italic text-sm text-neutral-500", "{i + 1}" }
                             td { class: TD_STYLE, "{t.name}" }
                             td { class: TD_STYLE, "{t.lang}" }
+                            td { class: TD_STYLE, {t.metadata.version.as_deref().unwrap_or("-")} }
+                            td { class: TD_STYLE, {t.description().unwrap_or("-")} }
+                            td { class: TD_STYLE, {t.metadata.keywords.as_deref().unwrap_or("-")} }
+                            td { class: TD_STYLE, {t.tags.as_deref().unwrap_or("-")} }
+                            td { class: TD_STYLE, "{t.visibility}" }
                             td { class: TD_STYLE, "{t.repo}" }
                             td { class: TD_STYLE,
                                 match &t.subdir {
@@ -79,7 +97,39 @@ pub fn Templates() -> Element {
         document::Title { "Boilermaker" }
         div { class: "py-4 px-2",
             h1 { class: "text-2xl text-neutral-500", "My Templates" }
+            div { class: "mt-4",
+                input {
+                    name: "query",
+                    r#type: "search",
+                    class: SEARCH_INPUT_STYLE,
+                    placeholder: "Search, e.g. axum lang:rust tag:backend path:Dockerfile",
+                    oninput: move |e| query.set(e.value()),
+                    value: "{query}",
+                }
+            }
+            if !tags.is_empty() {
+                div { class: "mt-3 flex flex-wrap gap-2",
+                    for t in tags {
+                        TagChip { active: tag.as_deref() == Some(t.as_str()), tag: t }
+                    }
+                }
+            }
             { content }
         }
     }
 }
+
+// Toggles the tag filter of the templates list.
+#[component]
+fn TagChip(tag: String, active: bool) -> Element {
+    let mut templates_ctx = use_context::<TemplatesContext>();
+    let style = if active {
+        CHIP_ACTIVE_STYLE
+    } else {
+        CHIP_STYLE
+    };
+
+    rsx! {
+        button { class: style, onclick: move |_| templates_ctx.toggle_tag(&tag), "{tag}" }
+    }
+}
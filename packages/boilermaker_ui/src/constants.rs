@@ -22,6 +22,10 @@ pub const BTN_GREEN_STYLE: &str =
 pub const BTN_RED_STYLE: &str =
     "bg-neutral-300 hover:bg-red-700 dark:bg-neutral-700 text-white py-1 px-2 rounded";
 
+pub const CHIP_STYLE: &str = "text-sm py-0.5 px-3 rounded-full border border-neutral-300 dark:border-neutral-700 text-neutral-500 dark:text-neutral-300 hover:border-blue-400";
+pub const CHIP_ACTIVE_STYLE: &str =
+    "text-sm py-0.5 px-3 rounded-full border border-blue-400 bg-blue-400 text-white";
+
 pub const TH_STYLE: &str = "p-2 text-left text-blue-400";
 pub const TH_MUTED_STYLE: &str = "p-2 text-left text-neutral-400";
 pub const TD_STYLE: &str = "p-2 border-b border-b-neutral-700";